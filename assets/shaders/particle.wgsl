@@ -8,12 +8,18 @@
 @group(2) @binding(1) var<uniform> color: vec4f;
 @group(2) @binding(2) var<uniform> xy_bounds: vec4<i32>;
 @group(2) @binding(3) var<uniform> z_scale: f32;
+@group(2) @binding(4) var<uniform> xy_bounds_lo: vec4<u32>;
+// values in [highlight.x, highlight.y] are highlighted, and the others dimmed
+@group(2) @binding(5) var<uniform> highlight: vec2f;
+// otherwise, nonzero values are highlighted if set, as for comparisons
+@group(2) @binding(6) var<uniform> mismatches: u32;
 
 struct Vertex {
     @builtin(vertex_index) vertex_index: u32,
     @builtin(instance_index) instance_index: u32,
-    @location(0) x: i32,
-    @location(1) y: i32,
+    // (low, high) words of 64-bit coordinates
+    @location(0) x: vec2<u32>,
+    @location(1) y: vec2<u32>,
     @location(2) z: f32,
 }
 
@@ -22,32 +28,34 @@ struct VertexOutput {
     @location(0) color: vec4<f32>,
 }
 
+fn join(hi: i32, lo: u32) -> i64 {
+    return (i64(hi) << 32) | i64(lo);
+}
+
 // linear map f s.t. f(lb) = -1.0 and f(ub) = 1.0
-fn int_map(x: i32, lb: i32, ub: i32) -> f32 {
-    // f(x) = (2x - (lb + ub)) / (ub-lb)
-    // i32::MIN + 1 == -i32::MAX
-    // min(2x - lb - ub) = 2 * i32::MIN - 2 * i32::MAX = -2 * u32::MAX
-    // max(2x - lb - ub) = 2 * i32::MAX - 2 * i32::MIN =  2 * u32::MAX
+fn int_map(x: i64, lb: i64, ub: i64) -> f32 {
+    // f(x) = 2 (x - lb) / (ub - lb) - 1
+    // the differences may not fit in i64, but they do in u64 when lb <= x <= ub
+    // points outside the bounds are waiting to be relocated
 
-    if lb == ub {
-        if x == lb {
-            return 0.0;
-        } else if x < lb {
-            return -2.0;
-        } else {
-            return 2.0;
-        }
+    if x < lb {
+        return -2.0;
+    } else if x > ub {
+        return 2.0;
+    } else if lb == ub {
+        return 0.0;
     }
 
-    let y = i64(x) + i64(x) - i64(lb) - i64(ub);
-    return f32(y) / f32(u32(ub - lb));
+    return 2.0 * f32(u64(x - lb)) / f32(u64(ub - lb)) - 1.0;
 }
 
 @vertex
 fn vertex(vertex: Vertex) -> VertexOutput {
     var out: VertexOutput;
-    let x = int_map(vertex.x, xy_bounds.x, xy_bounds.z);
-    let y = int_map(vertex.y, xy_bounds.y, xy_bounds.w);
+    let vx = join(i32(vertex.x.y), vertex.x.x);
+    let vy = join(i32(vertex.y.y), vertex.y.x);
+    let x = int_map(vx, join(xy_bounds.x, xy_bounds_lo.x), join(xy_bounds.z, xy_bounds_lo.z));
+    let y = int_map(vy, join(xy_bounds.y, xy_bounds_lo.y), join(xy_bounds.w, xy_bounds_lo.w));
     var z = vertex.z * z_scale;
 
     // world position
@@ -56,7 +64,32 @@ fn vertex(vertex: Vertex) -> VertexOutput {
 
     // distance to camera, to scale the brightness
     let d = wp.xyz - mesh_view_bindings::view.world_position;
-    out.color = color * inverseSqrt(dot(d,d));
+    var c = color;
+    if highlight.x <= highlight.y {
+        if highlight.x <= vertex.z && vertex.z <= highlight.y {
+            c = vec4f(1.0, 1.0, 1.0, 0.0);
+        } else {
+            c *= 0.2;
+        }
+    } else if mismatches != 0u {
+        // NaN also differs from 0
+        if !(vertex.z == 0.0) {
+            c = vec4f(1.0, 1.0, 1.0, 0.0);
+        } else {
+            c *= 0.2;
+        }
+    }
+    out.color = c * inverseSqrt(dot(d,d));
+
+    // instances past the first draw the edge cases as crosses, a few pixels wide and at full brightness
+    if vertex.instance_index > 0u {
+        let k = vertex.instance_index - 1u;
+        var arm = array<vec2f, 4>(vec2f(1.0, 0.0), vec2f(-1.0, 0.0), vec2f(0.0, 1.0), vec2f(0.0, -1.0));
+        let px = arm[k % 4u] * f32(1u + k / 4u);
+        let viewport = mesh_view_bindings::view.viewport.zw;
+        out.clip_position += vec4f(2.0 * px / viewport * out.clip_position.w, 0.0, 0.0);
+        out.color = c;
+    }
     return out;
 }
 
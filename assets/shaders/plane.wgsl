@@ -9,24 +9,31 @@
 @group(2) @binding(2) var<uniform> x_axis: vec3f;
 @group(2) @binding(3) var<uniform> y_axis: vec3f;
 @group(2) @binding(4) var<uniform> inputbounds: vec4<i32>;
+@group(2) @binding(5) var<uniform> inputbounds_lo: vec4<u32>;
+@group(2) @binding(6) var<uniform> bits: u32;
+
+fn join(hi: i32, lo: u32) -> i64 {
+    return (i64(hi) << 32) | i64(lo);
+}
 
 // linear map f s.t. f(-1.0) = lb and f(1.0) = ub
 // assumes |x| <= 1.0
-fn f2i(x: f32, bounds: vec2<i32>) -> i32 {
-    let lb = bounds.x;
-    let ub = bounds.y;
-
+fn f2i(x: f32, lb: i64, ub: i64) -> i64 {
     if lb == ub {
         return lb;
     }
 
-    let a = f32(u32(ub - lb));
-    let b = i64(lb) + i64(ub);
-
-    let ax = (x * a + f32(b & 1)) * 0.5;
+    let w = u64(ub - lb);
+    let d = u64(round((x + 1.0) * 0.5 * f32(w)));
+    return lb + i64(min(d, w));
+}
 
-    let y = i64(round(ax)) + (b >> 1);
-    return i32(y);
+fn clz64(x: u64) -> u32 {
+    let hi = u32(x >> 32);
+    if hi != 0 {
+        return countLeadingZeros(hi);
+    }
+    return 32 + countLeadingZeros(u32(x & 0xfffffffflu));
 }
 
 struct Vertex {
@@ -90,17 +97,22 @@ fn fragment(
     }
 
     if all(abs(xy) <= vec2(2.0)) {
-        var n: u32 = 0;
+        let lb = vec2(join(inputbounds.x, inputbounds_lo.x), join(inputbounds.y, inputbounds_lo.y));
+        let ub = vec2(join(inputbounds.z, inputbounds_lo.z), join(inputbounds.w, inputbounds_lo.w));
+        // only the top `bits` bits are shown
+        let shift = 64 - bits;
+
+        var n: u64 = 0;
         var k: u32 = 0;
         var m: u32 = 0;
         if abs(y) <= 1.0 {
-            n = u32(f2i(y, inputbounds.yw));
-            k = u32(32.0 * (abs(x) - 1.0));
-            m = countLeadingZeros(u32(inputbounds.w - inputbounds.y));
+            n = u64(f2i(y, lb.y, ub.y)) >> shift;
+            k = u32(f32(bits) * (abs(x) - 1.0));
+            m = clz64(u64(ub.y - lb.y) >> shift) - shift;
         } else if abs(x) <= 1.0 {
-            n = u32(f2i(x, inputbounds.xz));
-            k = u32(32.0 * (abs(y) - 1.0));
-            m = countLeadingZeros(u32(inputbounds.z - inputbounds.x));
+            n = u64(f2i(x, lb.x, ub.x)) >> shift;
+            k = u32(f32(bits) * (abs(y) - 1.0));
+            m = clz64(u64(ub.x - lb.x) >> shift) - shift;
         }
 
         let bit = (n >> k) & 1;
@@ -108,7 +120,7 @@ fn fragment(
         let zero = vec4f(0.01,0.01,0.01,1.0);
         let one  = vec4f(0.1,0.1,0.1,1.0);
 
-        if k + 7 < 32 - m {        
+        if k + 7 < bits - m {        
             return zero + 0.1 * one;
         } else if bit != 0 {
             return one;
@@ -1,7 +1,8 @@
-@group(0) @binding(0) var<uniform> size: u32;
+// (number of updates, 32-bit words per element)
+@group(0) @binding(0) var<uniform> size: vec2<u32>;
 @group(0) @binding(1) var<storage, read_write> idxs: array<u32>;
-@group(0) @binding(2) var<storage, read_write> src: array<i32>;
-@group(0) @binding(3) var<storage, read_write> dst: array<i32>;
+@group(0) @binding(2) var<storage, read_write> src: array<u32>;
+@group(0) @binding(3) var<storage, read_write> dst: array<u32>;
 
 struct ComputeInputs {
     @builtin(local_invocation_id) id: vec3<u32>,
@@ -14,9 +15,12 @@ struct ComputeInputs {
 @compute @workgroup_size(256)
 fn main(ctx: ComputeInputs) {
     var k = ctx.gid.x;
-    while (k < size) {
+    let w = size.y;
+    while (k < size.x) {
         let idx = idxs[k];
-        dst[idx] = src[k];
+        for (var j = 0u; j < w; j++) {
+            dst[idx * w + j] = src[k * w + j];
+        }
         k += 256u;
     }
 }
\ No newline at end of file
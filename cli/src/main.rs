@@ -0,0 +1,249 @@
+//! Evaluate the test functions over a region without opening a window
+//!
+//! The region is given as bit prefixes of the 64-bit input coordinates,
+//! like the `x:`/`y:` rows of the visualizer: a sign (`+` or `-`)
+//! followed by the next most significant bits (`0` or `1`).
+//! Functions taking `f32` inputs see the high 32 bits of each coordinate.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    process::ExitCode,
+};
+
+use lib::{Estimator, FuncDesc, InputType, Kind, NanMode, Timer, Timing, TimingMode, MAX_REPETITIONS};
+use rand::{distributions::Uniform, rngs::StdRng, Rng, SeedableRng};
+
+const USAGE: &str = "\
+usage: cphaze-cli [options]
+
+options:
+  -x PREFIX         bit prefix of x, e.g. +0111 (default: unconstrained)
+  -y PREFIX         bit prefix of y
+  -n COUNT          number of samples (default: 65536)
+  -f ID|NAME        function to evaluate, may be repeated (default: all)
+  --format csv|bin  output format (default: csv)
+  --nan any|sign|payload
+                    which NaN results comparisons tell apart (default: any)
+  --timer rdtsc|rdtscp|instant|perf_event
+                    clock of the timing functions (default: rdtsc)
+  --repetitions K   measurements per timed point, at most 64 (default: 1)
+  --estimator median|trimmed
+                    how the measurements are combined (default: median)
+  --mode throughput|latency
+                    whether timed calls are independent or each waits for
+                    the previous result (default: throughput)
+  -o FILE           output file (default: stdout)
+  --seed SEED       seed of the sampling, in hex, to repeat a run of this tool;
+                    the visualizer samples differently from the same seed
+                    (default: random, printed to stderr)
+  --list            list the available functions and exit
+
+The binary format is a sequence of little-endian records
+  x: i64, y: i64, z: f32 for each selected function, in order
+The CSV format starts with `# seed = SEED` and `# input = f32|f64` comment
+lines, and gives x and y as the values the functions see, in decimal and as
+IEEE bits: f32 if all selected functions take f32 inputs, else f64
+";
+
+struct Args {
+    x: (i64, i64),
+    y: Option<(i64, i64)>,
+    count: usize,
+    funcs: Vec<String>,
+    binary: bool,
+    nan: NanMode,
+    timing: Timing,
+    output: Option<String>,
+    seed: u64,
+    list: bool,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        x: (i64::MIN, i64::MAX),
+        y: None,
+        count: 1 << 16,
+        funcs: vec![],
+        binary: false,
+        nan: NanMode::Any,
+        timing: Timing::default(),
+        output: None,
+        seed: rand::random(),
+        list: false,
+    };
+    let mut it = std::env::args().skip(1);
+    while let Some(arg) = it.next() {
+        let mut value = || it.next().ok_or(format!("missing value for {arg}"));
+        match arg.as_str() {
+            "-x" => args.x = prefix_range(&value()?)?,
+            "-y" => args.y = Some(prefix_range(&value()?)?),
+            "-n" => args.count = value()?.parse().map_err(|e| format!("invalid count: {e}"))?,
+            "-f" => args.funcs.push(value()?),
+            "--format" => args.binary = match value()?.as_str() {
+                "csv" => false,
+                "bin" => true,
+                f => return Err(format!("unknown format {f:?}")),
+            },
+            "--nan" => args.nan = match value()?.as_str() {
+                "any" => NanMode::Any,
+                "sign" => NanMode::Sign,
+                "payload" => NanMode::Payload,
+                m => return Err(format!("unknown NaN mode {m:?}")),
+            },
+            "--timer" => {
+                let name = value()?;
+                args.timing.timer = Timer::ALL.into_iter()
+                    .find(|t| t.name() == name)
+                    .ok_or(format!("unknown timer {name:?}"))?;
+            }
+            "--repetitions" => {
+                let k: u32 = value()?.parse().map_err(|e| format!("invalid repetitions: {e}"))?;
+                if !(1..=MAX_REPETITIONS).contains(&k) {
+                    return Err(format!("repetitions must be from 1 to {MAX_REPETITIONS}"));
+                }
+                args.timing.repetitions = k;
+            }
+            "--estimator" => args.timing.estimator = match value()?.as_str() {
+                "median" => Estimator::Median,
+                "trimmed" => Estimator::TrimmedMean,
+                e => return Err(format!("unknown estimator {e:?}")),
+            },
+            "--mode" => {
+                let name = value()?;
+                args.timing.mode = TimingMode::ALL.into_iter()
+                    .find(|m| m.name() == name)
+                    .ok_or(format!("unknown timing mode {name:?}"))?;
+            }
+            "-o" => args.output = Some(value()?),
+            "--seed" => args.seed = u64::from_str_radix(&value()?, 16).map_err(|e| format!("invalid seed: {e}"))?,
+            "--list" => args.list = true,
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                std::process::exit(0);
+            }
+            _ => return Err(format!("unexpected argument {arg:?}")),
+        }
+    }
+    Ok(args)
+}
+
+/// The range of coordinates starting with the given bits
+///
+/// Each constrained bit is set in `set` or `unset`, as in the visualizer.
+fn prefix_range(prefix: &str) -> Result<(i64, i64), String> {
+    if prefix.len() > 64 {
+        return Err(format!("prefix {prefix:?} is longer than 64 bits"));
+    }
+    let mut set = 0i64;
+    let mut unset = 0i64;
+    for (i, c) in prefix.chars().enumerate() {
+        let bit = (1u64 << (63 - i)) as i64;
+        match (i, c) {
+            (0, '+') | (1.., '1') => set |= bit,
+            (0, '-') | (1.., '0') => unset |= bit,
+            _ => return Err(format!("invalid character {c:?} in prefix {prefix:?}")),
+        }
+    }
+    Ok((set ^ i64::MIN, !unset ^ i64::MIN))
+}
+
+fn select(descs: &[FuncDesc], names: &[String]) -> Result<Vec<u32>, String> {
+    if names.is_empty() {
+        return Ok((0..descs.len() as u32).collect());
+    }
+    names.iter().map(|name| {
+        match name.parse::<u32>() {
+            Ok(id) if (id as usize) < descs.len() => Ok(id),
+            _ => descs.iter()
+                .position(|d| d.name == *name)
+                .map(|id| id as u32)
+                .ok_or(format!("unknown function {name:?}")),
+        }
+    }).collect()
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let descs = lib::functions();
+    if args.list {
+        for (id, d) in descs.iter().enumerate() {
+            println!("{id:3}  {:?} {:?} arity {}  {}", d.kind, d.input, d.arity, d.name);
+        }
+        return Ok(());
+    }
+    let ids = select(&descs, &args.funcs)?;
+
+    // unary functions ignore y, so it is pinned to 0 like in the 1D mode of the visualizer
+    let unary = ids.iter().all(|&id| descs[id as usize].arity == 1);
+    let y = match args.y {
+        Some(y) => y,
+        None if unary => (0, 0),
+        None => (i64::MIN, i64::MAX),
+    };
+
+    eprintln!("seed: {:016x}", args.seed);
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    let xs: Vec<i64> = (&mut rng).sample_iter(Uniform::new_inclusive(args.x.0, args.x.1)).take(args.count).collect();
+    let ys: Vec<i64> = (&mut rng).sample_iter(Uniform::new_inclusive(y.0, y.1)).take(args.count).collect();
+    let xs32: Vec<i32> = xs.iter().map(|&x| (x >> 32) as i32).collect();
+    let ys32: Vec<i32> = ys.iter().map(|&y| (y >> 32) as i32).collect();
+
+    // a noise is given along with its timing, which is evaluated once for both
+    let source = |id: u32| descs.iter().position(|d| d.noise == Some(id)).map_or(id, |s| s as u32);
+    let mut results = HashMap::new();
+    for &id in &ids {
+        let id = source(id);
+        if results.contains_key(&id) { continue }
+        let d = &descs[id as usize];
+        let mut r = vec![0.0; args.count];
+        let mut spread = if d.noise.is_some_and(|n| ids.contains(&n)) { vec![0.0; args.count] } else { vec![] };
+        match (d.kind, d.input) {
+            (Kind::Comparison, _) => lib::compare_batched(&xs, &ys, &mut r, id, args.nan),
+            (_, InputType::F32) => lib::test_batched(&xs32, &ys32, &mut r, &mut spread, id, args.timing),
+            (_, InputType::F64) => lib::test_batched64(&xs, &ys, &mut r, &mut spread, id, args.timing),
+        }
+        results.insert(id, (r, spread));
+    }
+    let zs: Vec<Vec<f32>> = ids.iter().map(|&id| {
+        let (r, spread) = &results[&source(id)];
+        if descs[id as usize].kind == Kind::Noise { spread.clone() } else { r.clone() }
+    }).collect();
+
+    let out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map_err(|e| format!("{path}: {e}"))?),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut out = BufWriter::new(out);
+    let names: Vec<&str> = ids.iter().map(|&id| descs[id as usize].name.as_str()).collect();
+    if args.binary {
+        write_bin(&mut out, &xs, &ys, &zs)
+    } else {
+        let f32_only = ids.iter().all(|&id| descs[id as usize].input == InputType::F32);
+        let input = if f32_only { InputType::F32 } else { InputType::F64 };
+        lib::write_csv(&mut out, args.seed, input, &names, &xs, &ys, &zs)
+    }.and_then(|()| out.flush()).map_err(|e| e.to_string())
+}
+
+fn write_bin(out: &mut impl Write, xs: &[i64], ys: &[i64], zs: &[Vec<f32>]) -> io::Result<()> {
+    for k in 0..xs.len() {
+        out.write_all(&xs[k].to_le_bytes())?;
+        out.write_all(&ys[k].to_le_bytes())?;
+        for z in zs {
+            out.write_all(&z[k].to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let result = parse_args().and_then(run);
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}\n");
+            eprint!("{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! The CSV format written by `cphaze-cli` and the export of the visualizer
+
+use std::io::{self, Write};
+
+use crate::{InputType, Total32, Total64};
+
+/// Write samples as CSV, after `# seed = SEED` and `# input = f32|f64` comment lines
+///
+/// The inputs are positions in the total order, as sampled, and are written as the
+/// values the functions see at the precision of `input`: in decimal, which reads back
+/// exactly, followed by their bits in hex, which also keep the payloads of NaNs.
+pub fn write_csv(
+    out: &mut impl Write,
+    seed: u64,
+    input: InputType,
+    names: &[impl AsRef<str>],
+    xs: &[i64],
+    ys: &[i64],
+    zs: &[Vec<f32>],
+) -> io::Result<()> {
+    writeln!(out, "# seed = {seed:016x}")?;
+    writeln!(out, "# input = {}", match input {
+        InputType::F32 => "f32",
+        InputType::F64 => "f64",
+    })?;
+    write!(out, "x,y,x_bits,y_bits")?;
+    for name in names {
+        write!(out, ",{}", name.as_ref())?;
+    }
+    writeln!(out)?;
+    let value = |x: i64| match input {
+        InputType::F32 => {
+            let v = f32::from(Total32((x >> 32) as i32));
+            (format!("{v:e}"), format!("{:#010x}", v.to_bits()))
+        }
+        InputType::F64 => {
+            let v = f64::from(Total64(x));
+            (format!("{v:e}"), format!("{:#018x}", v.to_bits()))
+        }
+    };
+    for k in 0..xs.len() {
+        let (x, x_bits) = value(xs[k]);
+        let (y, y_bits) = value(ys[k]);
+        write!(out, "{x},{y},{x_bits},{y_bits}")?;
+        for z in zs {
+            write!(out, ",{}", z[k])?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
@@ -1,5 +1,14 @@
 
-use core::arch::asm;
+use std::{cell::Cell, hint::black_box};
+
+mod csv;
+mod reference;
+mod timer;
+mod total;
+
+pub use csv::write_csv;
+pub use timer::Timer;
+pub use total::{ParseTotalError, Total32, Total64};
 
 pub fn test_red(x: f32, y: f32) -> f32 {
     let f: fn(f32,f32) -> f32 = std::hint::black_box(libm::atan2f);
@@ -14,7 +23,109 @@ pub fn test_blue(x: f32, y: f32) -> f32 {
     test_green(x,y) / test_red(x,y)
 }
 
+pub fn test_expf(x: f32) -> f32 {
+    let f: fn(f32) -> f32 = std::hint::black_box(libm::expf);
+    time(x,0.0,|x,_| f(x))
+}
+pub fn test_sinf(x: f32) -> f32 {
+    let f: fn(f32) -> f32 = std::hint::black_box(libm::sinf);
+    time(x,0.0,|x,_| f(x))
+}
+pub fn test_log1pf(x: f32) -> f32 {
+    let f: fn(f32) -> f32 = std::hint::black_box(libm::log1pf);
+    time(x,0.0,|x,_| f(x))
+}
+
+pub fn test_atan2_libm(x: f64, y: f64) -> f32 {
+    let f: fn(f64,f64) -> f64 = std::hint::black_box(libm::atan2);
+    time(x,y,f)
+}
+pub fn test_atan2_std(x: f64, y: f64) -> f32 {
+    let f: fn(f64,f64) -> f64 = std::hint::black_box(|x,y| x.atan2(y));
+    time(x,y,f)
+}
+pub fn test_hypot_libm(x: f64, y: f64) -> f32 {
+    let f: fn(f64,f64) -> f64 = std::hint::black_box(libm::hypot);
+    time(x,y,f)
+}
+pub fn test_pow_libm(x: f64, y: f64) -> f32 {
+    let f: fn(f64,f64) -> f64 = std::hint::black_box(libm::pow);
+    time(x,y,f)
+}
+
+
+
+/// Signed error of `libm::atan2f` in ULPs, against the MPFR reference
+pub fn ulp_red(x: f32, y: f32) -> f32 {
+    ulp_error(libm::atan2f(x,y), reference::atan2f(x,y))
+}
+/// Signed error of `f32::atan2` in ULPs, against the MPFR reference
+pub fn ulp_green(x: f32, y: f32) -> f32 {
+    ulp_error(x.atan2(y), reference::atan2f(x,y))
+}
+
+/// Signed error of `libm::expf` in ULPs, against the MPFR reference
+pub fn ulp_expf(x: f32) -> f32 {
+    ulp_error(libm::expf(x), reference::expf(x))
+}
+
+/// Signed error of `libm::atan2` in ULPs, against the MPFR reference
+pub fn ulp_atan2_libm(x: f64, y: f64) -> f32 {
+    ulp_error64(libm::atan2(x,y), reference::atan2(x,y))
+}
+
+/// The signed distance from `reference` to `value` in the total order,
+/// which for finite results is the error in units in the last place.
+///
+/// Two NaNs compare as exact, but a NaN on only one side gives NaN.
+pub fn ulp_error(value: f32, reference: f32) -> f32 {
+    match (value.is_nan(), reference.is_nan()) {
+        (true, true) => 0.0,
+        (false, false) => {
+            let d = Total32::from(value).0 as i64 - Total32::from(reference).0 as i64;
+            d as f32
+        }
+        _ => f32::NAN,
+    }
+}
+
+/// Like `ulp_error`, for `f64`
+pub fn ulp_error64(value: f64, reference: f64) -> f32 {
+    match (value.is_nan(), reference.is_nan()) {
+        (true, true) => 0.0,
+        (false, false) => {
+            let d = Total64::from(value).0 as i128 - Total64::from(reference).0 as i128;
+            d as f32
+        }
+        _ => f32::NAN,
+    }
+}
 
+/// How NaN results are told apart by `bit_distance`
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum NanMode {
+    /// Every NaN is the same
+    #[default]
+    Any,
+    /// NaNs of the same sign are the same, whatever their payload
+    Sign,
+    /// NaNs are the same only with the same bits
+    Payload,
+}
+
+/// The signed distance from `b` to `a` in the total order, 0 when their bits
+/// are the same, after NaNs are made alike as selected by `nan`
+pub fn bit_distance(a: f32, b: f32, nan: NanMode) -> f32 {
+    let key = |v: f32| {
+        let v = match nan {
+            NanMode::Any if v.is_nan() => f32::from_bits(0x7fc0_0000),
+            NanMode::Sign if v.is_nan() => f32::from_bits(0x7fc0_0000).copysign(v),
+            _ => v,
+        };
+        Total32::from(v).0 as i64
+    };
+    (key(a) - key(b)) as f32
+}
 
 pub fn test_red_i32(x: i32, y: i32) -> f32 {
     test_red(Total32(x).into(), Total32(y).into())
@@ -28,114 +139,447 @@ pub fn test_blue_i32(x: i32, y: i32) -> f32 {
     test_blue(Total32(x).into(), Total32(y).into())
 }
 
+pub fn value_red_i32(x: i32, y: i32) -> f32 {
+    libm::atan2f(Total32(x).into(), Total32(y).into())
+}
+
+pub fn value_green_i32(x: i32, y: i32) -> f32 {
+    f32::atan2(Total32(x).into(), Total32(y).into())
+}
+
+pub fn ulp_red_i32(x: i32, y: i32) -> f32 {
+    ulp_red(Total32(x).into(), Total32(y).into())
+}
+
+pub fn ulp_green_i32(x: i32, y: i32) -> f32 {
+    ulp_green(Total32(x).into(), Total32(y).into())
+}
+
+// unary functions ignore their second argument
+pub fn test_expf_i32(x: i32, _y: i32) -> f32 {
+    test_expf(Total32(x).into())
+}
+
+pub fn test_sinf_i32(x: i32, _y: i32) -> f32 {
+    test_sinf(Total32(x).into())
+}
+
+pub fn test_log1pf_i32(x: i32, _y: i32) -> f32 {
+    test_log1pf(Total32(x).into())
+}
+
+pub fn ulp_expf_i32(x: i32, _y: i32) -> f32 {
+    ulp_expf(Total32(x).into())
+}
+
+pub fn test_atan2_libm_i64(x: i64, y: i64) -> f32 {
+    test_atan2_libm(Total64(x).into(), Total64(y).into())
+}
+
+pub fn test_atan2_std_i64(x: i64, y: i64) -> f32 {
+    test_atan2_std(Total64(x).into(), Total64(y).into())
+}
+
+pub fn test_hypot_libm_i64(x: i64, y: i64) -> f32 {
+    test_hypot_libm(Total64(x).into(), Total64(y).into())
+}
+
+pub fn test_pow_libm_i64(x: i64, y: i64) -> f32 {
+    test_pow_libm(Total64(x).into(), Total64(y).into())
+}
+
+pub fn ulp_atan2_libm_i64(x: i64, y: i64) -> f32 {
+    ulp_atan2_libm(Total64(x).into(), Total64(y).into())
+}
+
 #[no_mangle]
 pub fn int_fmt(x: i32) -> String {
     format!("{}", Total32(x))
 }
 
 #[no_mangle]
-pub fn test_batched(x: &[i32], y: &[i32], r: &mut [f32], id: u32) {
-    match id {
-        0 => test_batched_gen(x,y,r,test_red_i32),
-        1 => test_batched_gen(x,y,r,test_green_i32),
-        2 => test_batched_gen(x,y,r,test_blue_i32),
-        _ => return,
+pub fn int_fmt64(x: i64) -> String {
+    format!("{}", Total64(x))
+}
+
+/// What the output of a test function measures
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Kind {
+    /// Elapsed time, in scaled cycles
+    Timing,
+    /// Error against a reference, in ULPs
+    Accuracy,
+    /// The value computed by the function
+    Value,
+    /// The distance between the bits of the values of two functions, 0 where they agree
+    Comparison,
+    /// The spread of the repeated measurements of a timing, in the same units,
+    /// given along with the timing by evaluating it
+    Noise,
+}
+
+/// How the integer inputs of a test function are interpreted
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum InputType {
+    F32,
+    F64,
+}
+
+impl InputType {
+    pub const fn bits(self) -> u32 {
+        match self {
+            InputType::F32 => 32,
+            InputType::F64 => 64,
+        }
+    }
+}
+
+/// Description of a test function, as seen by the visualizer
+#[derive(Clone, PartialEq, Debug)]
+pub struct FuncDesc {
+    pub name: String,
+    pub arity: u32,
+    pub input: InputType,
+    pub color: [f32; 3],
+    pub kind: Kind,
+    /// The id of the `Kind::Noise` function given along with this timing
+    pub noise: Option<u32>,
+}
+
+#[derive(Copy, Clone)]
+enum Eval {
+    F32(fn(i32, i32) -> f32),
+    F64(fn(i64, i64) -> f32),
+    /// The names of the two functions compared by `compare_batched`
+    Compare(&'static str, &'static str),
+    /// The names of two timing functions, of which the first time over the second is given
+    Ratio(&'static str, &'static str),
+}
+
+struct Entry {
+    name: &'static str,
+    arity: u32,
+    color: [f32; 3],
+    kind: Kind,
+    eval: Eval,
+}
+
+impl Entry {
+    fn desc(&self) -> FuncDesc {
+        FuncDesc {
+            name: self.name.to_owned(),
+            arity: self.arity,
+            input: match self.eval {
+                Eval::F32(_) => InputType::F32,
+                Eval::F64(_) => InputType::F64,
+                Eval::Compare(a, _) | Eval::Ratio(a, _) => entry(a).map_or(InputType::F32, |e| e.desc().input),
+            },
+            color: self.color,
+            kind: self.kind,
+            noise: None,
+        }
+    }
+}
+
+// The functions under test; the index into this table is the `id` passed to `test_batched`
+const FUNCTIONS: &[Entry] = &[
+    Entry { name: "atan2f/libm time", arity: 2, color: [1.0, 0.0, 0.0], kind: Kind::Timing, eval: Eval::F32(test_red_i32) },
+    Entry { name: "atan2/std time", arity: 2, color: [0.0, 1.0, 0.0], kind: Kind::Timing, eval: Eval::F32(test_green_i32) },
+    Entry { name: "std/libm time ratio", arity: 2, color: [0.0, 0.0, 1.0], kind: Kind::Timing, eval: Eval::Ratio("atan2/std time", "atan2f/libm time") },
+    Entry { name: "atan2f/libm ulp", arity: 2, color: [1.0, 1.0, 0.0], kind: Kind::Accuracy, eval: Eval::F32(ulp_red_i32) },
+    Entry { name: "atan2/std ulp", arity: 2, color: [0.0, 1.0, 1.0], kind: Kind::Accuracy, eval: Eval::F32(ulp_green_i32) },
+    Entry { name: "atan2f/libm", arity: 2, color: [1.0, 0.5, 0.0], kind: Kind::Value, eval: Eval::F32(value_red_i32) },
+    Entry { name: "atan2/std", arity: 2, color: [0.5, 1.0, 0.0], kind: Kind::Value, eval: Eval::F32(value_green_i32) },
+    Entry { name: "expf/libm time", arity: 1, color: [1.0, 0.0, 0.0], kind: Kind::Timing, eval: Eval::F32(test_expf_i32) },
+    Entry { name: "sinf/libm time", arity: 1, color: [0.0, 1.0, 0.0], kind: Kind::Timing, eval: Eval::F32(test_sinf_i32) },
+    Entry { name: "log1pf/libm time", arity: 1, color: [0.0, 0.0, 1.0], kind: Kind::Timing, eval: Eval::F32(test_log1pf_i32) },
+    Entry { name: "expf/libm ulp", arity: 1, color: [1.0, 1.0, 0.0], kind: Kind::Accuracy, eval: Eval::F32(ulp_expf_i32) },
+    Entry { name: "atan2/libm f64 time", arity: 2, color: [1.0, 0.0, 0.5], kind: Kind::Timing, eval: Eval::F64(test_atan2_libm_i64) },
+    Entry { name: "atan2/std f64 time", arity: 2, color: [0.0, 1.0, 0.5], kind: Kind::Timing, eval: Eval::F64(test_atan2_std_i64) },
+    Entry { name: "atan2/libm f64 ulp", arity: 2, color: [1.0, 1.0, 0.5], kind: Kind::Accuracy, eval: Eval::F64(ulp_atan2_libm_i64) },
+    Entry { name: "hypot/libm f64 time", arity: 2, color: [0.5, 0.0, 1.0], kind: Kind::Timing, eval: Eval::F64(test_hypot_libm_i64) },
+    Entry { name: "pow/libm f64 time", arity: 2, color: [1.0, 0.5, 1.0], kind: Kind::Timing, eval: Eval::F64(test_pow_libm_i64) },
+    Entry { name: "atan2f libm/std bits", arity: 2, color: [1.0, 0.0, 1.0], kind: Kind::Comparison, eval: Eval::Compare("atan2f/libm", "atan2/std") },
+];
+
+fn entry(name: &str) -> Option<&'static Entry> {
+    FUNCTIONS.iter().find(|e| e.name == name)
+}
+
+// The timings that are measured rather than derived from others, with their ids;
+// the noise of each is a function with an id following the table
+fn noise_sources() -> impl Iterator<Item = (usize, &'static Entry)> {
+    FUNCTIONS.iter().enumerate().filter(|(_, e)| e.kind == Kind::Timing && !matches!(e.eval, Eval::Ratio(..)))
+}
+
+#[no_mangle]
+pub fn functions() -> Vec<FuncDesc> {
+    let mut descs: Vec<FuncDesc> = FUNCTIONS.iter().map(Entry::desc).collect();
+    for (k, (id, e)) in noise_sources().enumerate() {
+        let noise = (FUNCTIONS.len() + k) as u32;
+        descs[id].noise = Some(noise);
+        descs.push(FuncDesc {
+            name: format!("{} noise", e.name),
+            color: e.color.map(|c| c / 2.0),
+            kind: Kind::Noise,
+            ..e.desc()
+        });
+    }
+    descs
+}
+
+/// How the timing functions measure
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Timing {
+    pub timer: Timer,
+    /// The number of measurements combined into each result, at most `MAX_REPETITIONS`
+    pub repetitions: u32,
+    pub estimator: Estimator,
+    pub mode: TimingMode,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self {
+            timer: Timer::default(),
+            repetitions: 1,
+            estimator: Estimator::default(),
+            mode: TimingMode::default(),
+        }
+    }
+}
+
+/// What the timed calls measure
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum TimingMode {
+    /// Independent calls on the same inputs, which the CPU may overlap
+    #[default]
+    Throughput,
+    /// Each call waits for the result of the previous one, through a dependency
+    /// on its first input that leaves the value unchanged
+    ///
+    /// The dependency adds an integer `and` and `xor` to each call.
+    Latency,
+}
+
+impl TimingMode {
+    pub const ALL: [Self; 2] = [Self::Throughput, Self::Latency];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Throughput => "throughput",
+            Self::Latency => "latency",
+        }
     }
 }
 
-pub fn test_batched_gen(x: &[i32], y: &[i32], r: &mut [f32], f: impl Fn(i32, i32) -> f32) {
+// Floats whose bits can be made to depend on another value
+trait Chain: Copy + Default {
+    /// `self`, after xor with the bits of `on` masked by `zero`, which must be 0
+    fn after(self, on: Self, zero: u64) -> Self;
+}
+
+impl Chain for f32 {
+    fn after(self, on: Self, zero: u64) -> Self {
+        f32::from_bits(self.to_bits() ^ (on.to_bits() & zero as u32))
+    }
+}
+
+impl Chain for f64 {
+    fn after(self, on: Self, zero: u64) -> Self {
+        f64::from_bits(self.to_bits() ^ (on.to_bits() & zero))
+    }
+}
+
+/// How the repeated measurements of a point are combined
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum Estimator {
+    #[default]
+    Median,
+    /// The mean of the middle half
+    TrimmedMean,
+}
+
+impl Estimator {
+    pub const ALL: [Self; 2] = [Self::Median, Self::TrimmedMean];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Median => "median",
+            Self::TrimmedMean => "trimmed mean",
+        }
+    }
+
+    // of the sorted, nonempty `t`
+    fn estimate(self, t: &[f32]) -> f32 {
+        let n = t.len();
+        match self {
+            Self::Median => median(t),
+            Self::TrimmedMean => {
+                let middle = &t[n / 4..n - n / 4];
+                middle.iter().sum::<f32>() / middle.len() as f32
+            }
+        }
+    }
+}
+
+// of the sorted, nonempty `t`
+fn median(t: &[f32]) -> f32 {
+    let n = t.len();
+    (t[(n - 1) / 2] + t[n / 2]) / 2.0
+}
+
+pub const MAX_REPETITIONS: u32 = 64;
+
+thread_local! {
+    // the settings of the batch being evaluated
+    static TIMING: Cell<Timing> = Cell::new(Timing::default());
+    // the spread of the measurements of the last timed call
+    static SPREAD: Cell<f32> = const { Cell::new(f32::NAN) };
+}
+
+/// Evaluate a function taking `f32` inputs; does nothing if `id` is not one
+///
+/// Unless `spread` is empty, it receives the noise of a timing at each point, from the
+/// same measurements as the time; NaN if the function has no `Kind::Noise` function.
+#[no_mangle]
+pub fn test_batched(x: &[i32], y: &[i32], r: &mut [f32], spread: &mut [f32], id: u32, timing: Timing) {
+    TIMING.set(timing);
+    spread.fill(f32::NAN);
+    match FUNCTIONS.get(id as usize).map(|e| e.eval) {
+        Some(Eval::F32(f)) => test_batched_spread(x,y,r,spread,f),
+        Some(Eval::Ratio(a, b)) => {
+            if let (Some(Eval::F32(a)), Some(Eval::F32(b))) = (entry(a).map(|e| e.eval), entry(b).map(|e| e.eval)) {
+                test_batched_gen(x,y,r,|x,y| a(x,y) / b(x,y))
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Like `test_batched`, for a function taking `f64` inputs
+#[no_mangle]
+pub fn test_batched64(x: &[i64], y: &[i64], r: &mut [f32], spread: &mut [f32], id: u32, timing: Timing) {
+    TIMING.set(timing);
+    spread.fill(f32::NAN);
+    match FUNCTIONS.get(id as usize).map(|e| e.eval) {
+        Some(Eval::F64(f)) => test_batched_spread(x,y,r,spread,f),
+        Some(Eval::Ratio(a, b)) => {
+            if let (Some(Eval::F64(a)), Some(Eval::F64(b))) = (entry(a).map(|e| e.eval), entry(b).map(|e| e.eval)) {
+                test_batched_gen(x,y,r,|x,y| a(x,y) / b(x,y))
+            }
+        }
+        _ => {}
+    }
+}
+
+// Like `test_batched_gen`, also keeping the spread of each timed call unless `spread` is empty
+fn test_batched_spread<T: Copy>(x: &[T], y: &[T], r: &mut [f32], spread: &mut [f32], f: fn(T, T) -> f32) {
+    if spread.is_empty() {
+        return test_batched_gen(x,y,r,f);
+    }
     let n = x.len();
     assert_eq!(n, y.len());
     assert_eq!(n, r.len());
+    assert_eq!(n, spread.len());
 
     for k in 0..n {
+        SPREAD.set(f32::NAN);
         r[k] = f(x[k], y[k]);
+        spread[k] = SPREAD.get();
     }
 }
 
+/// Evaluate a comparison at 64-bit coordinates, of which functions taking `f32`
+/// inputs see the high words; does nothing if `id` is not one
+#[no_mangle]
+pub fn compare_batched(x: &[i64], y: &[i64], r: &mut [f32], id: u32, nan: NanMode) {
+    let Some(&Entry { eval: Eval::Compare(a, b), .. }) = FUNCTIONS.get(id as usize) else { return };
+    let (Some(a), Some(b)) = (values(a, x, y), values(b, x, y)) else { return };
+    for k in 0..r.len() {
+        r[k] = bit_distance(a[k], b[k], nan);
+    }
+}
 
-// repetitions to time
+// The results of the function `name` at 64-bit coordinates
+fn values(name: &str, x: &[i64], y: &[i64]) -> Option<Vec<f32>> {
+    let mut r = vec![0.0; x.len()];
+    match entry(name)?.eval {
+        Eval::F32(f) => {
+            let hi = |v: &[i64]| -> Vec<i32> { v.iter().map(|&v| (v >> 32) as i32).collect() };
+            test_batched_gen(&hi(x), &hi(y), &mut r, f)
+        }
+        Eval::F64(f) => test_batched_gen(x, y, &mut r, f),
+        Eval::Compare(..) | Eval::Ratio(..) => return None,
+    }
+    Some(r)
+}
+
+pub fn test_batched_gen<T: Copy>(x: &[T], y: &[T], r: &mut [f32], f: impl Fn(T, T) -> f32) {
+    let n = x.len();
+    assert_eq!(n, y.len());
+    assert_eq!(n, r.len());
+
+    for k in 0..n {
+        r[k] = f(x[k], y[k]);
+    }
+}
+
+
+// calls per measurement
 const N: usize = 1 << 5;
-const S: f32 = 1.0 / 512.0 / N as f32;
-fn time(x: f32, y: f32, f: impl Fn(f32,f32) -> f32) -> f32 {
-    unsafe {
+// timing results are in units of this many ticks per call, to be about as high as the other functions
+const TICKS_PER_UNIT: f32 = 512.0;
+/// The ticks of the selected timer per call of `f`, scaled; NaN if the timer is not available
+///
+/// Each of the repeated measurements times `N` calls. Their median absolute deviation,
+/// scaled to estimate their standard deviation for normal noise, is left in `SPREAD`;
+/// NaN with fewer than 3 measurements.
+fn time<T: Chain>(x: T, y: T, f: impl Fn(T,T) -> T) -> f32 {
+    let timing = TIMING.get();
+    let k = timing.repetitions.clamp(1, MAX_REPETITIONS) as usize;
+    let mut t = [0.0; MAX_REPETITIONS as usize];
+    for t in &mut t[..k] {
         let mut xs = [x; N];
         let mut ys = [y; N];
-        let mut zs = [0.0; N];
-        let t0: u32;
-        let t1: u32;
-        asm!(
-            "/* {} {} */",
-            "mfence",
-            "lfence",
-            "rdtsc",
-            in(reg) &mut xs,
-            in(reg) &mut ys,
-            out("eax") t0,
-            out("edx") _,
-            options(preserves_flags, nostack),
-        );
-        for i in 0..N {
-            zs[i] = f(xs[i],ys[i]);
-        }
-        asm!(
-            "/* {} */",
-            "lfence",
-            "rdtsc",
-            "lfence",
-            in(reg) &zs,
-            out("eax") t1,
-            out("edx") _,
-            options(preserves_flags, nostack),
-        );
-        (t1.wrapping_sub(t0) as f32) * S
-    }
-}
-
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
-#[repr(transparent)]
-struct Total32(pub i32);
-
-impl std::fmt::Display for Total32 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let s = self.0;
-        write!(f, "{}", if s < 0 { '-' } else { '+' })?;
-        let s = s ^ (s >> 31);
-
-        let exp = s >> 23;
-        let sig = s & ((1 << 23) - 1);
-
-        if s == 0 {
-            write!(f, "0x0.000000")?;
-        } else if exp == 255 {
-            if sig == 0 {
-                write!(f, "Inf")?;
-            } else {
-                let quiet = sig >= (1 << 22);
-                write!(f, "{}NaN(0x{:06x})",
-                    if quiet { 'q' } else { 's' },
-                    s & ((1 << 22) - 1)
-                )?;
+        let mut zs = [T::default(); N];
+        // the inputs are in place before the timer starts, and the results before it stops
+        black_box((&mut xs, &mut ys));
+        let m = match timing.mode {
+            TimingMode::Throughput => timing.timer.measure(|| {
+                for i in 0..N {
+                    zs[i] = f(xs[i],ys[i]);
+                }
+                black_box(&zs);
+            }),
+            TimingMode::Latency => {
+                // the compiler cannot tell that the masked result leaves x as it is
+                let zero = black_box(0);
+                let mut x = xs[0];
+                timing.timer.measure(|| {
+                    let mut z = T::default();
+                    for _ in 0..N {
+                        z = f(x, ys[0]);
+                        x = x.after(z, zero);
+                    }
+                    black_box(z);
+                })
             }
-        } else {
-            write!(f, "0x{}.{:06x}p{}",
-                exp.min(1),
-                2 * sig,
-                (exp - 127).max(-126)
-            )?;
-        }
-        Ok(())
+        };
+        let Some(m) = m else { return f32::NAN };
+        *t = m / N as f32 / TICKS_PER_UNIT;
     }
-}
-impl From<f32> for Total32 {
-    fn from(value: f32) -> Self {
-        let i = value.to_bits() as i32;
-        Self(i ^ (i >> 31) ^ (i & (1 << 31)))
+    let t = &mut t[..k];
+    t.sort_by(f32::total_cmp);
+    if k >= 3 {
+        let m = median(t);
+        let mut dev = [0.0; MAX_REPETITIONS as usize];
+        let dev = &mut dev[..k];
+        for (d, v) in dev.iter_mut().zip(&*t) {
+            *d = (v - m).abs();
+        }
+        dev.sort_by(f32::total_cmp);
+        SPREAD.set(1.4826 * median(dev));
     }
+    timing.estimator.estimate(t)
 }
-impl From<Total32> for f32 {
-    fn from(Total32(i): Total32) -> Self {
-        let u = i ^ (i >> 31) ^ (i & (1 << 31));
-        f32::from_bits(u as u32)
-    }
-}
\ No newline at end of file
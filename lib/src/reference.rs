@@ -0,0 +1,43 @@
+//! Correctly rounded reference implementations, evaluated with MPFR
+
+use std::cmp::Ordering;
+
+use rug::{float::Round, Float};
+
+const PREC: u32 = f32::MANTISSA_DIGITS;
+const PREC64: u32 = f64::MANTISSA_DIGITS;
+
+/// Round an MPFR result computed at `PREC` bits to the nearest `f32`,
+/// taking the direction of the first rounding into account so that
+/// results in the subnormal range are not double-rounded.
+fn to_f32(mut r: Float, dir: Ordering) -> f32 {
+    r.subnormalize_ieee_round(dir, Round::Nearest);
+    r.to_f32()
+}
+
+/// Like `to_f32`, for results computed at `PREC64` bits
+fn to_f64(mut r: Float, dir: Ordering) -> f64 {
+    r.subnormalize_ieee_round(dir, Round::Nearest);
+    r.to_f64()
+}
+
+/// atan2(y, x), with the same argument order as `libm::atan2f`
+pub fn atan2f(y: f32, x: f32) -> f32 {
+    let mut r = Float::with_val(PREC, y);
+    let dir = r.atan2_round(&Float::with_val(PREC, x), Round::Nearest);
+    to_f32(r, dir)
+}
+
+/// e^x, as `libm::expf`
+pub fn expf(x: f32) -> f32 {
+    let mut r = Float::with_val(PREC, x);
+    let dir = r.exp_round(Round::Nearest);
+    to_f32(r, dir)
+}
+
+/// atan2(y, x), with the same argument order as `libm::atan2`
+pub fn atan2(y: f64, x: f64) -> f64 {
+    let mut r = Float::with_val(PREC64, y);
+    let dir = r.atan2_round(&Float::with_val(PREC64, x), Round::Nearest);
+    to_f64(r, dir)
+}
@@ -0,0 +1,195 @@
+//! The clocks read around timed calls
+//!
+//! Each timer counts in its own unit: reference cycles for the time-stamp counter,
+//! nanoseconds for `Instant` and core cycles for `perf_event_open`. The cost of
+//! reading the timer twice in a row is measured once per thread and timer, and
+//! subtracted from the measurements, so that they only count the timed calls.
+
+use std::cell::Cell;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+/// The clock used by the timing functions
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum Timer {
+    /// The time-stamp counter, fenced with `mfence` and `lfence` (x86-64 only)
+    #[default]
+    Rdtsc,
+    /// The time-stamp counter read with `rdtscp`, which waits for the timed calls (x86-64 only)
+    Rdtscp,
+    /// `std::time::Instant`, in nanoseconds
+    Instant,
+    /// The cycle counter of the core, from `perf_event_open` (Linux only)
+    PerfEvent,
+}
+
+impl Timer {
+    pub const ALL: [Self; 4] = [Self::Rdtsc, Self::Rdtscp, Self::Instant, Self::PerfEvent];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Rdtsc => "rdtsc",
+            Self::Rdtscp => "rdtscp",
+            Self::Instant => "instant",
+            Self::PerfEvent => "perf_event",
+        }
+    }
+
+    /// A reading of the timer before or after the timed calls, if available
+    pub(crate) fn read(self, start: bool) -> Option<u64> {
+        match self {
+            Self::Rdtsc => tsc(start),
+            Self::Rdtscp => tscp(),
+            Self::Instant => {
+                thread_local! {
+                    static EPOCH: Instant = Instant::now();
+                }
+                Some(EPOCH.with(|e| e.elapsed().as_nanos() as u64))
+            }
+            Self::PerfEvent => cycles(),
+        }
+    }
+
+    /// The ticks taken by reading the timer around `f`, less the calibrated overhead
+    pub(crate) fn measure(self, f: impl FnOnce()) -> Option<f32> {
+        let overhead = self.overhead()?;
+        Some(self.ticks(f)? as f32 - overhead)
+    }
+
+    fn ticks(self, f: impl FnOnce()) -> Option<u64> {
+        let t0 = self.read(true)?;
+        f();
+        let t1 = self.read(false)?;
+        Some(t1.wrapping_sub(t0))
+    }
+
+    // The fewest ticks between two readings, over a few tries
+    fn overhead(self) -> Option<f32> {
+        thread_local! {
+            static OVERHEAD: Cell<[Option<f32>; 4]> = const { Cell::new([None; 4]) };
+        }
+        let k = self as usize;
+        if let Some(o) = OVERHEAD.get()[k] {
+            return Some(o);
+        }
+        let o = (0..64).map(|_| self.ticks(|| ())).min()?? as f32;
+        OVERHEAD.set({
+            let mut v = OVERHEAD.get();
+            v[k] = Some(o);
+            v
+        });
+        Some(o)
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn tsc(start: bool) -> Option<u64> {
+    use core::arch::x86_64::{_mm_lfence, _mm_mfence, _rdtsc};
+    // SAFETY: the fences and the time-stamp counter are part of x86-64
+    unsafe {
+        if start {
+            _mm_mfence();
+            _mm_lfence();
+            Some(_rdtsc())
+        } else {
+            _mm_lfence();
+            let t = _rdtsc();
+            _mm_lfence();
+            Some(t)
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn tscp() -> Option<u64> {
+    use core::arch::x86_64::{__rdtscp, _mm_lfence};
+    if !has_rdtscp() { return None }
+    // SAFETY: `rdtscp` is available, as checked above, and the fence is part of x86-64
+    unsafe {
+        let mut aux = 0;
+        let t = __rdtscp(&mut aux);
+        _mm_lfence();
+        Some(t)
+    }
+}
+
+// Whether the CPU reports `rdtscp`, which some virtual machines hide
+//
+// `is_x86_feature_detected` does not know it, so it is asked of CPUID once.
+#[cfg(target_arch = "x86_64")]
+fn has_rdtscp() -> bool {
+    use core::arch::x86_64::{__cpuid, __get_cpuid_max};
+    static HAS: OnceLock<bool> = OnceLock::new();
+    *HAS.get_or_init(|| {
+        // SAFETY: CPUID is part of x86-64, and the extended leaf is checked to exist;
+        // newer toolchains no longer mark these as unsafe
+        #[allow(unused_unsafe)]
+        unsafe {
+            let (max, _) = __get_cpuid_max(0x8000_0000);
+            max >= 0x8000_0001 && __cpuid(0x8000_0001).edx & 1 << 27 != 0
+        }
+    })
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn tsc(_start: bool) -> Option<u64> {
+    None
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn tscp() -> Option<u64> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn cycles() -> Option<u64> {
+    // The start of `struct perf_event_attr`, as of its first published size
+    #[repr(C)]
+    struct PerfEventAttr {
+        kind: u32,
+        size: u32,
+        config: u64,
+        sample_period: u64,
+        sample_type: u64,
+        read_format: u64,
+        flags: u64,
+        wakeup_events: u32,
+        bp_type: u32,
+        config1: u64,
+    }
+    const PERF_TYPE_HARDWARE: u32 = 0;
+    const PERF_COUNT_HW_CPU_CYCLES: u64 = 0;
+    // exclude_kernel and exclude_hv, so that it is allowed without privileges
+    const FLAGS: u64 = 1 << 5 | 1 << 6;
+
+    thread_local! {
+        // the counter of this thread, or -1 if it could not be opened
+        static FD: i32 = {
+            let attr = PerfEventAttr {
+                kind: PERF_TYPE_HARDWARE,
+                size: size_of::<PerfEventAttr>() as u32,
+                config: PERF_COUNT_HW_CPU_CYCLES,
+                sample_period: 0,
+                sample_type: 0,
+                read_format: 0,
+                flags: FLAGS,
+                wakeup_events: 0,
+                bp_type: 0,
+                config1: 0,
+            };
+            // SAFETY: `attr` is a valid `perf_event_attr` of the given size
+            unsafe { libc::syscall(libc::SYS_perf_event_open, &attr, 0, -1, -1, 0) as i32 }
+        };
+    }
+    let fd = FD.with(|&fd| fd);
+    if fd < 0 { return None }
+    let mut count = 0u64;
+    // SAFETY: reads 8 bytes into `count`
+    let n = unsafe { libc::read(fd, (&raw mut count).cast(), 8) };
+    (n == 8).then_some(count)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cycles() -> Option<u64> {
+    None
+}
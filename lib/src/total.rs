@@ -0,0 +1,352 @@
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[repr(transparent)]
+pub struct Total32(pub i32);
+
+impl std::fmt::Display for Total32 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = self.0;
+        write!(f, "{}", if s < 0 { '-' } else { '+' })?;
+        let s = s ^ (s >> 31);
+
+        let exp = s >> 23;
+        let sig = s & ((1 << 23) - 1);
+
+        if s == 0 {
+            write!(f, "0x0.000000")?;
+        } else if exp == 255 {
+            if sig == 0 {
+                write!(f, "Inf")?;
+            } else {
+                let quiet = sig >= (1 << 22);
+                write!(f, "{}NaN(0x{:06x})",
+                    if quiet { 'q' } else { 's' },
+                    s & ((1 << 22) - 1)
+                )?;
+            }
+        } else {
+            write!(f, "0x{}.{:06x}p{}",
+                exp.min(1),
+                2 * sig,
+                (exp - 127).max(-126)
+            )?;
+        }
+        Ok(())
+    }
+}
+impl From<f32> for Total32 {
+    fn from(value: f32) -> Self {
+        let i = value.to_bits() as i32;
+        Self(i ^ (i >> 31) ^ (i & (1 << 31)))
+    }
+}
+impl From<Total32> for f32 {
+    fn from(Total32(i): Total32) -> Self {
+        let u = i ^ (i >> 31) ^ (i & (1 << 31));
+        f32::from_bits(u as u32)
+    }
+}
+
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[repr(transparent)]
+pub struct Total64(pub i64);
+
+impl std::fmt::Display for Total64 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = self.0;
+        write!(f, "{}", if s < 0 { '-' } else { '+' })?;
+        let s = s ^ (s >> 63);
+
+        let exp = s >> 52;
+        let sig = s & ((1 << 52) - 1);
+
+        if s == 0 {
+            write!(f, "0x0.0000000000000")?;
+        } else if exp == 2047 {
+            if sig == 0 {
+                write!(f, "Inf")?;
+            } else {
+                let quiet = sig >= (1 << 51);
+                write!(f, "{}NaN(0x{:013x})",
+                    if quiet { 'q' } else { 's' },
+                    s & ((1 << 51) - 1)
+                )?;
+            }
+        } else {
+            write!(f, "0x{}.{:013x}p{}",
+                exp.min(1),
+                sig,
+                (exp - 1023).max(-1022)
+            )?;
+        }
+        Ok(())
+    }
+}
+impl From<f64> for Total64 {
+    fn from(value: f64) -> Self {
+        let i = value.to_bits() as i64;
+        Self(i ^ (i >> 63) ^ (i & (1 << 63)))
+    }
+}
+impl From<Total64> for f64 {
+    fn from(Total64(i): Total64) -> Self {
+        let u = i ^ (i >> 63) ^ (i & (1 << 63));
+        f64::from_bits(u as u64)
+    }
+}
+
+/// The error returned when parsing a `Total32` or `Total64` fails
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseTotalError;
+
+impl std::fmt::Display for ParseTotalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid float literal")
+    }
+}
+
+impl std::error::Error for ParseTotalError {}
+
+/// Parses decimal and hex-float literals, `inf` and `nan`,
+/// as well as everything printed by `Display`
+impl std::str::FromStr for Total32 {
+    type Err = ParseTotalError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (neg, mag) = parse_bits(s, 23, 127, |d| d.parse::<f32>().ok().map(|f| f.to_bits() as u64))?;
+        Ok(Self::from(f32::from_bits(mag as u32 | (neg as u32) << 31)))
+    }
+}
+
+/// Parses decimal and hex-float literals, `inf` and `nan`,
+/// as well as everything printed by `Display`
+impl std::str::FromStr for Total64 {
+    type Err = ParseTotalError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (neg, mag) = parse_bits(s, 52, 1023, |d| d.parse::<f64>().ok().map(f64::to_bits))?;
+        Ok(Self::from(f64::from_bits(mag | (neg as u64) << 63)))
+    }
+}
+
+// The sign and the IEEE bits of the magnitude, for a format with `sig`
+// fraction bits and exponent bias `bias`. Decimals are left to `dec`.
+fn parse_bits(
+    s: &str,
+    sig: u32,
+    bias: i32,
+    dec: impl Fn(&str) -> Option<u64>,
+) -> Result<(bool, u64), ParseTotalError> {
+    let s = s.trim();
+    let (neg, s) = match s.as_bytes().first() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let inf = ((2 * bias + 1) as u64) << sig;
+    let lower = s.to_ascii_lowercase();
+
+    if lower == "inf" || lower == "infinity" {
+        return Ok((neg, inf));
+    }
+    if lower == "nan" {
+        return Ok((neg, inf | 1 << (sig - 1)));
+    }
+    if let Some(rest) = lower.strip_prefix("qnan(0x").or(lower.strip_prefix("snan(0x")) {
+        let payload = rest.strip_suffix(')')
+            .and_then(|p| u64::from_str_radix(p, 16).ok())
+            .filter(|&p| p < 1 << (sig - 1))
+            .ok_or(ParseTotalError)?;
+        let quiet = lower.starts_with('q');
+        if !quiet && payload == 0 {
+            return Err(ParseTotalError);
+        }
+        return Ok((neg, inf | (quiet as u64) << (sig - 1) | payload));
+    }
+    if let Some(hex) = lower.strip_prefix("0x") {
+        return parse_hex(hex, sig, bias).map(|mag| (neg, mag)).ok_or(ParseTotalError);
+    }
+    // the sign has been taken already
+    if s.starts_with(['+', '-']) {
+        return Err(ParseTotalError);
+    }
+    dec(s).map(|mag| (neg, mag)).ok_or(ParseTotalError)
+}
+
+// Hex-float digits after the `0x`, rounded to nearest, ties to even
+fn parse_hex(s: &str, sig: u32, bias: i32) -> Option<u64> {
+    let (digits, exp) = match s.split_once('p') {
+        Some((d, e)) => (d, e.parse::<i64>().ok()?.clamp(-1 << 20, 1 << 20)),
+        None => (s, 0),
+    };
+    let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+    if int.is_empty() && frac.is_empty() {
+        return None;
+    }
+    // the value is (m + sticky) * 2^e, with the digits not fitting in `m` only kept as `sticky`
+    let mut m = 0u64;
+    let mut e = exp;
+    let mut sticky = false;
+    for (c, is_frac) in int.chars().map(|c| (c, false)).chain(frac.chars().map(|c| (c, true))) {
+        let d = c.to_digit(16)? as u64;
+        if m >> 60 == 0 {
+            m = m << 4 | d;
+            e -= 4 * is_frac as i64;
+        } else {
+            sticky |= d != 0;
+            e += 4 * !is_frac as i64;
+        }
+    }
+    if m == 0 {
+        return Some(0);
+    }
+    let emin = 1 - bias as i64;
+    let top = 63 - m.leading_zeros() as i64 + e;
+    // the exponent of the last bit of the result
+    let q = top.max(emin) - sig as i64;
+
+    let shift = q - e;
+    let r = if shift <= 0 {
+        m << -shift
+    } else if shift < 128 {
+        let m = m as u128;
+        let r = m >> shift;
+        let rem = m & ((1 << shift) - 1);
+        let half = 1 << (shift - 1);
+        let up = rem > half || (rem == half && (sticky || r & 1 == 1));
+        (r + up as u128) as u64
+    } else {
+        0
+    };
+    let inf = ((2 * bias + 1) as u64) << sig;
+    let biased = q + sig as i64 + bias as i64 - 1;
+    if biased > 2 * bias as i64 {
+        return Some(inf);
+    }
+    Some((((biased as u64) << sig) + r).min(inf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bits32(s: &str) -> Option<u32> {
+        s.parse::<Total32>().ok().map(|t| f32::from(t).to_bits())
+    }
+
+    fn bits64(s: &str) -> Option<u64> {
+        s.parse::<Total64>().ok().map(|t| f64::from(t).to_bits())
+    }
+
+    // a fixed sequence of well mixed 64-bit values
+    fn mixed(n: usize) -> impl Iterator<Item = u64> {
+        let mut x = 0x9e37_79b9_7f4a_7c15u64;
+        std::iter::repeat_with(move || {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x
+        }).take(n)
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let edges = [0, 1, 0x007f_ffff, 0x0080_0000, 0x7f7f_ffff, 0x7f80_0000, 0x7f80_0001, 0x7fc0_0000, 0x7fff_ffff];
+        let spread = (0..=u32::MAX).step_by(9973);
+        for u in edges.into_iter().flat_map(|u| [u, u | 1 << 31]).chain(spread) {
+            let t = Total32::from(f32::from_bits(u));
+            assert_eq!(t.to_string().parse(), Ok(t), "{u:#010x} printed as {t}");
+        }
+        for u in mixed(100_000).chain([0, 1, 0x7ff0_0000_0000_0000, 0x7ff0_0000_0000_0001, 0xfff8_0000_0000_0000]) {
+            let t = Total64::from(f64::from_bits(u));
+            assert_eq!(t.to_string().parse(), Ok(t), "{u:#018x} printed as {t}");
+        }
+    }
+
+    #[test]
+    fn decimals_match_std() {
+        let literals = [
+            "0", "-0", "0.1", "-2.5", "1e-45", "7e-46", "1.17549435e-38", "3.4028235e38",
+            "3.4028236e38", "1e39", "-1e39", "123456789", "2.7182818284590452353602874713527",
+        ];
+        for s in literals {
+            assert_eq!(bits32(s), Some(s.parse::<f32>().unwrap().to_bits()), "{s}");
+            assert_eq!(bits64(s), Some(s.parse::<f64>().unwrap().to_bits()), "{s}");
+        }
+        for u in mixed(10_000) {
+            let f = f32::from_bits(u as u32);
+            if f.is_finite() {
+                assert_eq!(bits32(&format!("{f:e}")), Some(f.to_bits()));
+            }
+            let f = f64::from_bits(u);
+            if f.is_finite() {
+                assert_eq!(bits64(&format!("{f:e}")), Some(f.to_bits()));
+            }
+        }
+        assert_eq!(bits32("1e"), None);
+        assert_eq!(bits32("--1"), None);
+        assert_eq!(bits32("+-1"), None);
+    }
+
+    #[test]
+    fn hex_rounds_to_nearest_even() {
+        assert_eq!(bits32("0x1.8p1"), Some(3.0f32.to_bits()));
+        assert_eq!(bits32("-0x.8"), Some((-0.5f32).to_bits()));
+        // half an ulp above 1 is a tie, going to the even 1
+        assert_eq!(bits32("0x1.000001p0"), Some(0x3f80_0000));
+        // one and a half ulps is a tie going up to the even 2 ulps
+        assert_eq!(bits32("0x1.000003p0"), Some(0x3f80_0002));
+        // a digit past the tie, or one beyond the 60 bits kept exactly, rounds up
+        assert_eq!(bits32("0x1.0000011p0"), Some(0x3f80_0001));
+        assert_eq!(bits32("0x1.00000100000000000000001p0"), Some(0x3f80_0001));
+        assert_eq!(bits32("0x1.0000010000000000000000p0"), Some(0x3f80_0000));
+        // leading zeros do not count against the kept bits
+        assert_eq!(bits32("0x0000000000000000000001.000001p0"), Some(0x3f80_0000));
+        assert_eq!(bits64("0x1.00000000000008p0"), Some(0x3ff0_0000_0000_0000));
+        assert_eq!(bits64("0x1.00000000000018p0"), Some(0x3ff0_0000_0000_0002));
+    }
+
+    #[test]
+    fn hex_overflows_to_inf() {
+        assert_eq!(bits32("0x1.fffffep127"), Some(f32::MAX.to_bits()));
+        // below the tie with the next power of two, which is past the largest exponent
+        assert_eq!(bits32("0x1.fffffefp127"), Some(f32::MAX.to_bits()));
+        assert_eq!(bits32("0x1.ffffffp127"), Some(f32::INFINITY.to_bits()));
+        assert_eq!(bits32("0x1p128"), Some(f32::INFINITY.to_bits()));
+        assert_eq!(bits32("-0x1p99999999"), Some(f32::NEG_INFINITY.to_bits()));
+        assert_eq!(bits64("0x1.fffffffffffffp1023"), Some(f64::MAX.to_bits()));
+        assert_eq!(bits64("0x1.fffffffffffff8p1023"), Some(f64::INFINITY.to_bits()));
+    }
+
+    #[test]
+    fn hex_subnormals() {
+        assert_eq!(bits32("0x1p-149"), Some(1));
+        assert_eq!(bits32("0x0.000002p-126"), Some(1));
+        // half the smallest subnormal is a tie, going to the even 0
+        assert_eq!(bits32("0x1p-150"), Some(0));
+        assert_eq!(bits32("-0x1p-150"), Some(1 << 31));
+        assert_eq!(bits32("0x1.00000000001p-150"), Some(1));
+        assert_eq!(bits32("0x1.8p-149"), Some(2));
+        assert_eq!(bits32("0x1p-151"), Some(0));
+        assert_eq!(bits32("0x1p-99999999"), Some(0));
+        assert_eq!(bits32("0x1.fffffcp-127"), Some(0x007f_ffff));
+        // half-way from the largest subnormal to the smallest normal
+        assert_eq!(bits32("0x1.fffffep-127"), Some(0x0080_0000));
+        assert_eq!(bits64("0x1p-1074"), Some(1));
+        assert_eq!(bits64("0x1p-1075"), Some(0));
+        assert_eq!(bits64("0x1.8p-1074"), Some(2));
+    }
+
+    #[test]
+    fn nan_payloads() {
+        assert_eq!(bits32("nan"), Some(0x7fc0_0000));
+        assert_eq!(bits32("-NaN"), Some(0xffc0_0000));
+        assert_eq!(bits32("inf"), Some(0x7f80_0000));
+        assert_eq!(bits32("-Infinity"), Some(0xff80_0000));
+        assert_eq!(bits32("qNaN(0x000001)"), Some(0x7fc0_0001));
+        assert_eq!(bits32("-sNaN(0x3fffff)"), Some(0xffbf_ffff));
+        assert_eq!(bits64("sNaN(0x0000000000001)"), Some(0x7ff0_0000_0000_0001));
+        // a signaling NaN needs a payload, which must fit below the quiet bit
+        assert_eq!(bits32("sNaN(0x000000)"), None);
+        assert_eq!(bits32("qNaN(0x400000)"), None);
+        assert_eq!(bits32("qNaN(0x1"), None);
+    }
+}
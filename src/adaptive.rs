@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use core::range::RangeInclusive;
+use lib::InputType;
+use rand::{rngs::StdRng, Rng};
+
+use crate::func_xy::{InputPoints, Region};
+
+/// Biases the random refresh towards interesting samples of one function
+///
+/// A sample is interesting if the magnitude of its value is above `threshold`,
+/// or if its value differs by more than `jump` times the value of a neighbour,
+/// the last sample evaluated in the same or an adjacent cell of a grid over the
+/// region. New samples are placed near interesting ones, except for a fraction
+/// `uniform` of them which follow the distribution of the region.
+#[derive(Resource)]
+pub struct Adaptive {
+    /// The name of the function whose values are followed
+    pub target: Option<String>,
+    pub threshold: f32,
+    pub jump: f32,
+    pub uniform: f32,
+    /// New samples are within `1 / 2^spread` of the region from their parent
+    pub spread: u32,
+    interesting: Vec<u32>,
+    // the position of each sample in `interesting`
+    positions: HashMap<u32, usize>,
+    // the next slot to overwrite when `interesting` is full
+    next: usize,
+    // the last sample evaluated in each cell of the grid, with its inputs
+    neighbours: HashMap<(u32, u32), (u32, i64, i64)>,
+    region: Option<(Region, InputType)>,
+}
+
+impl Default for Adaptive {
+    fn default() -> Self {
+        Self {
+            target: None,
+            threshold: f32::INFINITY,
+            jump: 0.5,
+            uniform: 0.25,
+            spread: 8,
+            interesting: vec![],
+            positions: HashMap::new(),
+            next: 0,
+            neighbours: HashMap::new(),
+            region: None,
+        }
+    }
+}
+
+// A point of `r` within `r.len() >> spread` of `p`, in whole inputs of the selected precision
+fn near(rng: &mut StdRng, p: i64, r: RangeInclusive<i64>, spread: u32, input: InputType) -> i64 {
+    let len = r.end.wrapping_sub(r.start) as u64;
+    let step = 1u64 << (64 - input.bits());
+    let radius = (len >> spread).max(step) as i64;
+    let lo = p.saturating_sub(radius).max(r.start);
+    let hi = p.saturating_add(radius).min(r.end);
+    rng.gen_range(lo..=hi)
+}
+
+// The cell of the grid over `r` containing `p`
+fn cell(p: i64, r: RangeInclusive<i64>) -> u32 {
+    let offset = p.wrapping_sub(r.start) as u64 as u128;
+    let len = r.end.wrapping_sub(r.start) as u64 as u128 + 1;
+    (offset * Adaptive::GRID as u128 / len) as u32
+}
+
+impl Adaptive {
+    const MAX_INTERESTING: usize = 1 << 14;
+    // cells of the grid of neighbours along each axis
+    const GRID: u32 = 1 << 8;
+
+    // samples of another region are of no use
+    fn follow_region(&mut self, points: &InputPoints) {
+        let region = (points.region(), points.input);
+        if self.region != Some(region) {
+            self.region = Some(region);
+            self.interesting.clear();
+            self.positions.clear();
+            self.next = 0;
+            self.neighbours.clear();
+        }
+    }
+
+    fn insert(&mut self, i: u32) {
+        if self.positions.contains_key(&i) { return }
+        if self.interesting.len() < Self::MAX_INTERESTING {
+            self.positions.insert(i, self.interesting.len());
+            self.interesting.push(i);
+        } else {
+            self.positions.remove(&self.interesting[self.next]);
+            self.positions.insert(i, self.next);
+            self.interesting[self.next] = i;
+            self.next = (self.next + 1) % Self::MAX_INTERESTING;
+        }
+    }
+
+    // sample `i` is drawn again, so it no longer stands for an interesting point
+    fn remove(&mut self, i: u32) {
+        let Some(k) = self.positions.remove(&i) else { return };
+        self.interesting.swap_remove(k);
+        if let Some(&moved) = self.interesting.get(k) {
+            self.positions.insert(moved, k);
+        }
+        if self.next > self.interesting.len() {
+            self.next = 0;
+        }
+    }
+
+    /// Pick the inputs of the new sample `i`
+    pub fn sample(&mut self, points: &mut InputPoints, i: u32) -> (i64, i64) {
+        self.follow_region(points);
+        self.remove(i);
+        if self.target.is_some() && !self.interesting.is_empty() && points.rng.r#gen::<f32>() >= self.uniform {
+            let p = self.interesting[points.rng.gen_range(0..self.interesting.len())];
+            if let (Some(&px), Some(&py)) = (points.x_vec.get(p as usize), points.y_vec.get(p as usize)) {
+                let x = near(&mut points.rng, px, points.x_range, self.spread, points.input);
+                let y = near(&mut points.rng, py, points.y_range, self.spread, points.input);
+                return (x, y);
+            }
+        }
+        let x = points.distribution.sample(&mut points.rng, points.x_range, points.input);
+        let y = points.distribution.sample(&mut points.rng, points.y_range, points.input);
+        (x, y)
+    }
+
+    /// Follow the new `values` of the target function at `idxs`
+    pub fn observe(&mut self, points: &InputPoints, idxs: &[u32], values: &[f32]) {
+        self.follow_region(points);
+        let value = |i: u32| values.get(i as usize).copied().unwrap_or(f32::NAN);
+        for &i in idxs {
+            let (Some(&x), Some(&y)) = (points.x_vec.get(i as usize), points.y_vec.get(i as usize)) else { continue };
+            let z = value(i);
+            let (cx, cy) = (cell(x, points.x_range), cell(y, points.y_range));
+            let mut jump = false;
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    let c = (cx.wrapping_add_signed(dx), cy.wrapping_add_signed(dy));
+                    let Some(&(n, nx, ny)) = self.neighbours.get(&c) else { continue };
+                    // the neighbour may have been drawn again since
+                    if n == i || points.x_vec.get(n as usize) != Some(&nx) || points.y_vec.get(n as usize) != Some(&ny) { continue }
+                    let zn = value(n);
+                    jump |= (z - zn).abs() > self.jump * zn.abs();
+                }
+            }
+            self.neighbours.insert((cx, cy), (i, x, y));
+            if z.abs() > self.threshold || jump {
+                self.insert(i);
+            }
+        }
+    }
+
+    /// Settings, with the functions that can be followed
+    pub fn ui<'a>(&mut self, ui: &mut egui::Ui, names: impl Iterator<Item = &'a str>) {
+        egui::ComboBox::from_label("follow")
+            .selected_text(self.target.as_deref().unwrap_or("off"))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.target, None, "off");
+                for name in names {
+                    ui.selectable_value(&mut self.target, Some(name.to_owned()), name);
+                }
+            });
+        ui.add_enabled_ui(self.target.is_some(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("threshold");
+                ui.add(egui::DragValue::new(&mut self.threshold).speed(0.1));
+            });
+            ui.add(egui::Slider::new(&mut self.jump, 0.01..=100.0).logarithmic(true).text("jump"));
+            ui.add(egui::Slider::new(&mut self.uniform, 0.0..=1.0).text("uniform fraction"));
+            ui.add(egui::Slider::new(&mut self.spread, 1..=63).text("spread"));
+            ui.label(format!("{} interesting samples", self.interesting.len()));
+        });
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use bevy::prelude::*;
+use bevy_egui::egui::{self, Widget};
+use lib::{InputType, Total32, Total64};
+
+use crate::func_xy::{hi, int_map, FuncXY, InputPoints, Region};
+
+/// Write the current samples and results to a file
+pub fn plugin(app: &mut App) {
+    app.init_resource::<Export>()
+        .add_systems(Update, export_points);
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    /// The same as the CSV output of `cphaze-cli`
+    Csv,
+    /// A structured array with fields `x`, `y` (`<f4` or `<f8`, as the input) and one `<f4` per function
+    Npy,
+    /// Vertices at the plotted position, with the inputs and one property per function
+    Ply,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Npy => "npy",
+            Format::Ply => "ply",
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct Export {
+    path: String,
+    format: Format,
+    requested: bool,
+    // the outcome of the last export
+    status: String,
+}
+
+impl Default for Export {
+    fn default() -> Self {
+        Self {
+            path: "cphaze.csv".into(),
+            format: Format::Csv,
+            requested: false,
+            status: String::new(),
+        }
+    }
+}
+
+impl Widget for &mut Export {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        ui.collapsing("Export", |ui| {
+            ui.horizontal(|ui| {
+                for (format, name) in [(Format::Csv, "CSV"), (Format::Npy, "NPY"), (Format::Ply, "PLY")] {
+                    if ui.selectable_value(&mut self.format, format, name).clicked() {
+                        let path = Path::new(&self.path).with_extension(format.extension());
+                        self.path = path.to_string_lossy().into_owned();
+                    }
+                }
+            });
+            ui.text_edit_singleline(&mut self.path);
+            self.requested |= ui.button("Export").clicked();
+            if !self.status.is_empty() {
+                ui.label(&self.status);
+            }
+        }).header_response
+    }
+}
+
+// The samples and results at the time of the export
+struct Snapshot {
+    seed: u64,
+    input: InputType,
+    region: Region,
+    names: Vec<String>,
+    xs: Vec<i64>,
+    ys: Vec<i64>,
+    zs: Vec<Vec<f32>>,
+}
+
+fn export_points(
+    mut export: ResMut<Export>,
+    points: Res<InputPoints>,
+    q_func: Query<&FuncXY>,
+) {
+    if !std::mem::take(&mut export.requested) { return }
+
+    let mut funcs: Vec<&FuncXY> = q_func.iter().filter(|f| f.desc.arity == points.arity()).collect();
+    funcs.sort_by_key(|f| f.id);
+
+    // samples that were not evaluated yet are left out
+    let idxs: Vec<usize> = (0..points.x_vec.len())
+        .filter(|&k| points.evaluated(k as u32))
+        .collect();
+    let snapshot = Snapshot {
+        seed: points.seed,
+        input: points.input,
+        region: points.region(),
+        names: funcs.iter().map(|f| f.desc.name.clone()).collect(),
+        xs: idxs.iter().map(|&k| points.x_vec[k]).collect(),
+        ys: idxs.iter().map(|&k| points.y_vec[k]).collect(),
+        zs: funcs.iter().map(|f| idxs.iter().map(|&k| f.value(k)).collect()).collect(),
+    };
+
+    export.status = match snapshot.write(&export.path, export.format) {
+        Ok(()) => {
+            info!("Exported {} points to {}", snapshot.xs.len(), export.path);
+            format!("exported {} points to {}", snapshot.xs.len(), export.path)
+        }
+        Err(e) => {
+            warn!("Export to {} failed: {e}", export.path);
+            format!("export failed: {e}")
+        }
+    };
+}
+
+impl Snapshot {
+    fn write(&self, path: &str, format: Format) -> io::Result<()> {
+        let Snapshot { seed, input, ref names, ref xs, ref ys, ref zs, .. } = *self;
+        let mut out = BufWriter::new(File::create(path)?);
+        match format {
+            Format::Csv => lib::write_csv(&mut out, seed, input, names, xs, ys, zs),
+            Format::Npy => write_npy(&mut out, input, names, xs, ys, zs),
+            Format::Ply => self.write_ply(&mut out),
+        }?;
+        out.flush()
+    }
+
+    fn write_ply(&self, out: &mut impl Write) -> io::Result<()> {
+        let Snapshot { ref xs, ref ys, ref zs, .. } = *self;
+        writeln!(out, "ply")?;
+        writeln!(out, "format binary_little_endian 1.0")?;
+        writeln!(out, "comment seed = {:016x}", self.seed)?;
+        for (k, name) in self.names.iter().enumerate() {
+            writeln!(out, "comment z{k} = {name}")?;
+        }
+        writeln!(out, "element vertex {}", xs.len())?;
+        for p in ["float x", "float y", "double x_input", "double y_input"] {
+            writeln!(out, "property {p}")?;
+        }
+        for k in 0..zs.len() {
+            writeln!(out, "property float z{k}")?;
+        }
+        writeln!(out, "end_header")?;
+
+        let value = |x: i64| match self.input {
+            InputType::F32 => f32::from(Total32(hi(x))) as f64,
+            InputType::F64 => f64::from(Total64(x)),
+        };
+        let (x_range, y_range) = self.region;
+        for k in 0..xs.len() {
+            out.write_all(&int_map(xs[k], x_range).to_le_bytes())?;
+            out.write_all(&int_map(ys[k], y_range).to_le_bytes())?;
+            out.write_all(&value(xs[k]).to_le_bytes())?;
+            out.write_all(&value(ys[k]).to_le_bytes())?;
+            for z in zs {
+                out.write_all(&z[k].to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// The bytes of the input at position `x` of the total order, at the selected precision
+fn input_bytes(input: InputType, x: i64) -> Vec<u8> {
+    match input {
+        InputType::F32 => f32::from(Total32(hi(x))).to_le_bytes().to_vec(),
+        InputType::F64 => f64::from(Total64(x)).to_le_bytes().to_vec(),
+    }
+}
+
+fn write_npy(out: &mut impl Write, input: InputType, names: &[String], xs: &[i64], ys: &[i64], zs: &[Vec<f32>]) -> io::Result<()> {
+    let float = match input {
+        InputType::F32 => "<f4",
+        InputType::F64 => "<f8",
+    };
+    let mut descr = format!("[('x', '{float}'), ('y', '{float}')");
+    for name in names {
+        let name = name.replace('\\', "\\\\").replace('\'', "\\'");
+        descr.push_str(&format!(", ('{name}', '<f4')"));
+    }
+    descr.push(']');
+    let mut header = format!("{{'descr': {descr}, 'fortran_order': False, 'shape': ({},), }}", xs.len());
+    // the magic, version, header length and header are padded to a multiple of 64 bytes
+    let unpadded = 10 + header.len() + 1;
+    header.extend(std::iter::repeat_n(' ', unpadded.next_multiple_of(64) - unpadded));
+    header.push('\n');
+    let header_len = u16::try_from(header.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many functions for an npy header"))?;
+
+    out.write_all(b"\x93NUMPY\x01\x00")?;
+    out.write_all(&header_len.to_le_bytes())?;
+    out.write_all(header.as_bytes())?;
+    // the records are packed, without padding
+    for k in 0..xs.len() {
+        out.write_all(&input_bytes(input, xs[k]))?;
+        out.write_all(&input_bytes(input, ys[k]))?;
+        for z in zs {
+            out.write_all(&z[k].to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
@@ -4,66 +4,330 @@ use std::{collections::BTreeSet, mem};
 use bevy::render::extract_resource::ExtractResource;
 use bevy::{prelude::*, render::storage::ShaderStorageBuffer};
 use bevy_egui::egui::{self, Slider, Widget};
-use rand::{distributions::Uniform, prelude::Distribution, rngs::StdRng, Rng, SeedableRng};
+use rand::{rngs::StdRng, SeedableRng};
 
 use bevy::render::render_resource::{AsBindGroup, BufferUsages};
 use bevy::render::render_asset::RenderAssetUsages;
 
+use lib::{FuncDesc, InputType, Kind, NanMode, Total32, Total64, TimingMode};
+
 use crate::point_cloud::PointCloudEntity;
+use crate::stats::Stats;
 use crate::plane::PlaneMaterial;
+use crate::sampling::{self, Distribution};
+use crate::incremental::Call;
 
 pub struct PluginXY;
 
 impl Plugin for PluginXY {
     fn build(&self, app: &mut App) {
         app.add_observer(change_region)
+            .init_resource::<RegionHistory>()
             .add_systems(Startup, setup)
-            .add_systems(Update, track_time);
+            .add_systems(Update, (sync_functions, clear_reseeded, enumerate_region, place_edge_cases, track_time).chain());
     }
 }
 
 /// A function to be evaluated at points of R^2
 #[derive(Component)]
 pub struct FuncXY {
+    /// Index into the table returned by `hot::functions`
     pub id: u32,
+    pub desc: FuncDesc,
     pub zs: Handle<ShaderStorageBuffer>,
+    /// The values sent to `zs`, for indices up to the last one evaluated
+    pub values: Vec<f32>,
+    /// Statistics of `values`, if enabled
+    pub stats: Option<Stats>,
+    /// The NaNs told apart, for comparisons
+    pub nan: NanMode,
+    /// Whether the calls are timed independently or in a dependent chain, for timings
+    pub timing_mode: TimingMode,
+}
+
+impl FuncXY {
+    /// Store the results for the given indices, as sent to `zs`
+    ///
+    /// `len` is the current number of samples.
+    pub fn store(&mut self, idxs: &[u32], r: &[f32], len: usize) {
+        if let Some(stats) = &mut self.stats {
+            stats.resize(&self.values, len);
+        }
+        for (&i, &z) in idxs.iter().zip(r) {
+            let i = i as usize;
+            if self.values.len() <= i {
+                self.values.resize(i + 1, f32::NAN);
+            }
+            if let Some(stats) = &mut self.stats {
+                stats.replace(self.values[i], z, i as u32);
+            }
+            self.values[i] = z;
+        }
+    }
+
+    /// What is needed to evaluate this function
+    pub fn call(&self) -> Call {
+        Call {
+            id: self.id,
+            kind: self.desc.kind,
+            input: self.desc.input,
+            nan: self.nan,
+            timing_mode: self.timing_mode,
+        }
+    }
+
+    /// Forget the results, once their samples have been discarded
+    pub fn clear(&mut self) {
+        self.values.clear();
+        if self.stats.is_some() {
+            self.stats = Some(Stats::new(&[], 0));
+        }
+    }
+
+    /// The value at index `i`, NaN if it has not been evaluated
+    pub fn value(&self, i: usize) -> f32 {
+        self.values.get(i).copied().unwrap_or(f32::NAN)
+    }
 }
 
 
 /// Event that modifies the input region
 #[derive(Event)]
 pub enum RegionUpdate {
-    StartX(i32),
-    StartY(i32),
-    EndX(i32),
-    EndY(i32),
+    StartX(i64),
+    StartY(i64),
+    EndX(i64),
+    EndY(i64),
     SetLen(u32),
 }
 
 
+/// The input region, as x and y ranges
+pub type Region = (RangeInclusive<i64>, RangeInclusive<i64>);
+
+/// Move from the `current` region to the given one
+pub fn set_region(commands: &mut Commands, current: Region, (x, y): Region) {
+    // the ends are moved in the order that keeps each range non-empty
+    if x.start > current.0.end {
+        commands.trigger(RegionUpdate::EndX(x.end));
+        commands.trigger(RegionUpdate::StartX(x.start));
+    } else {
+        commands.trigger(RegionUpdate::StartX(x.start));
+        commands.trigger(RegionUpdate::EndX(x.end));
+    }
+    if y.start > current.1.end {
+        commands.trigger(RegionUpdate::EndY(y.end));
+        commands.trigger(RegionUpdate::StartY(y.start));
+    } else {
+        commands.trigger(RegionUpdate::StartY(y.start));
+        commands.trigger(RegionUpdate::EndY(y.end));
+    }
+}
+
+/// Previously visited regions, for navigating back and forward
+#[derive(Resource, Default)]
+pub struct RegionHistory {
+    back: Vec<Region>,
+    forward: Vec<Region>,
+}
+
+impl RegionHistory {
+    /// Record `current` before moving to a new region
+    pub fn push(&mut self, current: Region) {
+        self.back.push(current);
+        self.forward.clear();
+    }
+
+    pub fn can_back(&self) -> bool {
+        !self.back.is_empty()
+    }
+
+    pub fn can_forward(&self) -> bool {
+        !self.forward.is_empty()
+    }
+
+    pub fn back(&mut self, current: Region) -> Option<Region> {
+        let r = self.back.pop()?;
+        self.forward.push(current);
+        Some(r)
+    }
+
+    pub fn forward(&mut self, current: Region) -> Option<Region> {
+        let r = self.forward.pop()?;
+        self.back.push(current);
+        Some(r)
+    }
+}
+
 /// The points sampled from the input region
+///
+/// Coordinates are 64-bit positions in the total order of `f64`.
+/// Functions taking `f32` inputs see only the high 32 bits,
+/// which is the position in the total order of `f32`.
 #[derive(Resource)]
 pub struct InputPoints {
-    pub x_vec: Vec<i32>,
-    pub y_vec: Vec<i32>,
+    pub x_vec: Vec<i64>,
+    pub y_vec: Vec<i64>,
 
     // indices of points that have been relocated and need updating
     pub modified: BTreeSet<u32>,
+    // indices of points being evaluated, whose stored results are of their previous positions
+    pub pending: BTreeSet<u32>,
+
+    pub x_range: RangeInclusive<i64>,
+    pub y_range: RangeInclusive<i64>,
 
-    pub x_range: RangeInclusive<i32>,
-    pub y_range: RangeInclusive<i32>,
+    // the precision used for displaying and selecting the region
+    pub input: InputType,
+    // how new samples are spread over the region
+    pub distribution: Distribution,
+
+    // sample only x, for functions of one argument; y is pinned to 0
+    pub one_dim: bool,
 
     pub xs: Handle<ShaderStorageBuffer>,
     pub ys: Handle<ShaderStorageBuffer>,
+    // the samples below this have had their positions uploaded to `xs` and `ys`
+    pub uploaded: u32,
 
     pub max_len: u32,
 
     pub refresh_random: bool,
     pub refresh_rate: u32,
+
+    // enumerate every point of the region instead, if there are at most `MAX_LEN`
+    pub exhaustive: bool,
+    // the region that `x_vec` and `y_vec` currently enumerate
+    pub enumerated: Option<(RangeInclusive<i64>, RangeInclusive<i64>, InputType)>,
+
+    // keep the grid of edge cases in the region in the first `edge_len` samples
+    pub edge_cases: bool,
+    pub edge_len: u32,
+
+    // all sampling goes through `rng`, so that a session can be reproduced from `seed`
+    pub seed: u64,
+    pub rng: StdRng,
+    // set by `reseed`, until the results of the discarded samples are cleared
+    pub reseeded: bool,
 }
 
 impl InputPoints {
-    const MAX_LEN: u32 = 1 << 20;
+    pub const MAX_LEN: u32 = 1 << 20;
+
+    /// The number of arguments of the functions being shown
+    pub fn arity(&self) -> u32 {
+        if self.one_dim { 1 } else { 2 }
+    }
+
+    pub fn region(&self) -> Region {
+        (self.x_range, self.y_range)
+    }
+
+    /// Widen `lo..=hi` to whole inputs at the selected precision
+    pub fn snap(&self, lo: i64, hi: i64) -> RangeInclusive<i64> {
+        let low_bits = ((1u64 << (64 - self.input.bits())) - 1) as i64;
+        (lo & !low_bits..=hi | low_bits).into()
+    }
+
+    /// The number of distinct inputs in `r` at the selected precision
+    pub fn axis_len(&self, r: RangeInclusive<i64>) -> u128 {
+        let shift = 64 - self.input.bits();
+        ((r.end >> shift) as i128 - (r.start >> shift) as i128 + 1) as u128
+    }
+
+    /// The `k`th distinct input in `r` at the selected precision
+    pub fn axis_point(&self, r: RangeInclusive<i64>, k: u64) -> i64 {
+        let shift = 64 - self.input.bits();
+        (((r.start >> shift) + k as i64) << shift).clamp(r.start, r.end)
+    }
+
+    /// Whether every point of the region has been evaluated
+    pub fn complete(&self) -> bool {
+        self.enumerated.is_some() && self.modified.is_empty() && self.pending.is_empty()
+    }
+
+    /// Whether the stored results at index `i` are of its current position
+    pub fn evaluated(&self, i: u32) -> bool {
+        !self.modified.contains(&i) && !self.pending.contains(&i)
+    }
+
+    /// Restart sampling from `seed`, discarding the current points
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+        self.x_vec.clear();
+        self.y_vec.clear();
+        self.modified.clear();
+        self.enumerated = None;
+        self.reseeded = true;
+    }
+
+    /// Format a coordinate as a float of the selected precision
+    pub fn fmt(&self, x: i64) -> String {
+        match self.input {
+            InputType::F32 => crate::hot::int_fmt(hi(x)),
+            InputType::F64 => crate::hot::int_fmt64(x),
+        }
+    }
+
+    /// Parse a float of the selected precision as a region bound
+    ///
+    /// For `f32`, the bound covers all the coordinates with that float as their high word.
+    pub fn parse_bound(&self, s: &str, end: bool) -> Option<i64> {
+        match self.input {
+            InputType::F32 => {
+                let x = (s.parse::<Total32>().ok()?.0 as i64) << 32;
+                Some(if end { x | u32::MAX as i64 } else { x })
+            }
+            InputType::F64 => Some(s.parse::<Total64>().ok()?.0),
+        }
+    }
+
+    /// Format a coordinate so that `parse_bound` gives it back
+    pub fn bound_text(&self, x: i64) -> String {
+        match self.input {
+            InputType::F32 => Total32(hi(x)).to_string(),
+            InputType::F64 => Total64(x).to_string(),
+        }
+    }
+
+    /// The region bounds, split into high and low words for the shaders
+    pub fn bounds(&self) -> (IVec4, UVec4) {
+        let b = [
+            self.x_range.start,
+            self.y_range.start,
+            self.x_range.end,
+            self.y_range.end,
+        ];
+        (b.map(hi).into(), b.map(|x| x as u32).into())
+    }
+}
+
+/// The position of `x` in the total order of `f32`
+pub fn hi(x: i64) -> i32 {
+    (x >> 32) as i32
+}
+
+/// Linear map f s.t. f(r.start) = -1.0 and f(r.end) = 1.0, like `int_map` in particle.wgsl
+pub fn int_map(x: i64, r: RangeInclusive<i64>) -> f32 {
+    if x < r.start {
+        -2.0
+    } else if x > r.end {
+        2.0
+    } else if r.start == r.end {
+        0.0
+    } else {
+        let d = x.wrapping_sub(r.start) as u64 as f64;
+        let w = r.end.wrapping_sub(r.start) as u64 as f64;
+        (2.0 * d / w - 1.0) as f32
+    }
+}
+
+/// Inverse of `int_map`, like `f2i` in plane.wgsl
+pub fn f2i(v: f32, r: RangeInclusive<i64>) -> i64 {
+    let w = r.end.wrapping_sub(r.start) as u64;
+    let d = ((v.clamp(-1.0, 1.0) as f64 + 1.0) * 0.5 * w as f64).round();
+    r.start.wrapping_add((d as u64).min(w) as i64)
 }
 
 
@@ -73,7 +337,59 @@ impl Widget for &mut InputPoints {
         ui.add(egui::Label::new("updates per frame:"));
         ui.add(egui::Slider::new(&mut self.refresh_rate, 0..=(1 << 17)).logarithmic(true));
         ui.add(egui::Checkbox::new(&mut self.refresh_random, "refresh random points"));
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.exhaustive, "enumerate small regions");
+            if self.complete() {
+                ui.colored_label(egui::Color32::GREEN, format!("complete: {} points", self.x_vec.len()));
+            } else if self.enumerated.is_some() {
+                ui.label(format!("enumerating {} points", self.x_vec.len()));
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.edge_cases, "include edge cases");
+            if self.edge_len > 0 {
+                ui.label(format!("{} points", self.edge_len));
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.input, InputType::F32, "f32");
+            ui.selectable_value(&mut self.input, InputType::F64, "f64");
+            ui.separator();
+            ui.checkbox(&mut self.one_dim, "1D");
+        });
+        let distribution = self.distribution;
+        egui::ComboBox::from_label("distribution")
+            .selected_text(distribution.name())
+            .show_ui(ui, |ui| {
+                for d in Distribution::ALL {
+                    ui.selectable_value(&mut self.distribution, d, d.name());
+                }
+            });
+        if self.distribution != distribution {
+            // the samples are drawn again from the same seed
+            self.reseed(self.seed);
+        }
         ui.add(egui::Slider::new(&mut self.max_len, (1 << 10)..=InputPoints::MAX_LEN).logarithmic(true));
+        ui.horizontal(|ui| {
+            ui.label("seed:");
+            // the text being edited is kept in egui's memory until it loses focus
+            let id = ui.id().with("seed");
+            let mut text = ui.data_mut(|d| d.get_temp::<String>(id))
+                .unwrap_or_else(|| format!("{:016x}", self.seed));
+            let r = ui.add(egui::TextEdit::singleline(&mut text).desired_width(128.0).font(egui::TextStyle::Monospace));
+            if r.lost_focus() {
+                ui.data_mut(|d| d.remove::<String>(id));
+                match u64::from_str_radix(text.trim(), 16) {
+                    Ok(seed) if seed != self.seed => self.reseed(seed),
+                    _ => (),
+                }
+            } else if r.has_focus() {
+                ui.data_mut(|d| d.insert_temp(id, text));
+            }
+            if ui.button("new").clicked() {
+                self.reseed(rand::random());
+            }
+        });
         ui.response()
     }
 }
@@ -83,36 +399,97 @@ pub fn setup(
     mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
 ) {
 
-    let mut xs = ShaderStorageBuffer::with_size(InputPoints::MAX_LEN as usize * size_of::<i32>(), RenderAssetUsages::RENDER_WORLD);
-    xs.set_data(vec![0; InputPoints::MAX_LEN as usize]);
+    // 64-bit coordinates are stored as (low, high) pairs of 32-bit words
+    let mut xs = ShaderStorageBuffer::with_size(InputPoints::MAX_LEN as usize * size_of::<UVec2>(), RenderAssetUsages::RENDER_WORLD);
+    xs.set_data(vec![UVec2::ZERO; InputPoints::MAX_LEN as usize]);
     xs.buffer_description.usage |= BufferUsages::VERTEX;
     let xs = buffers.add(xs);
-    let mut ys = ShaderStorageBuffer::with_size(InputPoints::MAX_LEN as usize * size_of::<i32>(), RenderAssetUsages::RENDER_WORLD);
-    ys.set_data(vec![0; InputPoints::MAX_LEN as usize]);
+    let mut ys = ShaderStorageBuffer::with_size(InputPoints::MAX_LEN as usize * size_of::<UVec2>(), RenderAssetUsages::RENDER_WORLD);
+    ys.set_data(vec![UVec2::ZERO; InputPoints::MAX_LEN as usize]);
     ys.buffer_description.usage |= BufferUsages::VERTEX;
     let ys = buffers.add(ys);
 
+    let seed = rand::random();
     commands.insert_resource(InputPoints {
         xs: xs.clone(),
         ys: ys.clone(),
+        uploaded: 0,
         modified: default(),
+        pending: default(),
         x_vec: vec![],
         y_vec: vec![],
 
-        x_range: (i32::MIN..=i32::MAX).into(),
-        y_range: (i32::MIN..=i32::MAX).into(), 
+        x_range: (i64::MIN..=i64::MAX).into(),
+        y_range: (i64::MIN..=i64::MAX).into(), 
+        input: InputType::F32,
+        distribution: Distribution::default(),
+        one_dim: false,
         max_len: InputPoints::MAX_LEN,
 
         refresh_random: true,
         refresh_rate: 100,
+
+        exhaustive: false,
+        enumerated: None,
+
+        edge_cases: false,
+        edge_len: 0,
+
+        seed,
+        rng: StdRng::seed_from_u64(seed),
+        reseeded: false,
     });
     
     commands.add_observer(spawn_points);
+}
 
-    commands.trigger(Spawn(0, LinearRgba::new(1.0,0.0,0.0,0.0)));
-    commands.trigger(Spawn(1, LinearRgba::new(0.0,1.0,0.0,0.0)));
-    commands.trigger(Spawn(2, LinearRgba::new(0.0,0.0,1.0,0.0)));
+/// Match the `FuncXY` entities to the function table of the currently loaded lib
+pub fn sync_functions(
+    mut commands: Commands,
+    mut version: Local<Option<usize>>,
+    mut q_func: Query<(Entity, &mut FuncXY, &MeshMaterial3d<ParticleMaterial>)>,
+    mut materials: ResMut<Assets<ParticleMaterial>>,
+) {
+    let v = crate::hot::version();
+    if *version == Some(v) { return }
+    *version = Some(v);
+
+    let descs = crate::hot::functions();
+    let mut present = vec![false; descs.len()];
+
+    for (entity, mut func, mat) in q_func.iter_mut() {
+        match descs.iter().position(|d| d.name == func.desc.name) {
+            Some(id) if !present[id] => {
+                present[id] = true;
+                // a changed default color replaces the current one, which may have been edited
+                if func.desc.color != descs[id].color && let Some(mat) = materials.get_mut(mat) {
+                    let [r, g, b] = descs[id].color;
+                    mat.color = LinearRgba::new(r, g, b, 0.0);
+                }
+                func.id = id as u32;
+                func.desc = descs[id].clone();
+            }
+            _ => commands.entity(entity).despawn(),
+        }
+    }
+    for (id, desc) in descs.into_iter().enumerate() {
+        if !present[id] {
+            commands.trigger(Spawn(id as u32, desc));
+        }
+    }
+}
+/// Clear the results of the samples discarded by `InputPoints::reseed`
+pub fn clear_reseeded(
+    mut points: ResMut<InputPoints>,
+    mut q_func: Query<&mut FuncXY>,
+) {
+    if !points.reseeded { return }
+    points.reseeded = false;
+    for mut func in &mut q_func {
+        func.clear();
+    }
 }
+
 pub fn track_time(
     t: Res<Time<Virtual>>,
     input: Res<InputPoints>,
@@ -121,30 +498,103 @@ pub fn track_time(
 
     mut q_points: Query<&mut PointCloudEntity>,
 ) {
-    let bounds = [
-        input.x_range.start,
-        input.y_range.start,
-        input.x_range.end,
-        input.y_range.end,
-    ].into();
+    let (bounds, bounds_lo) = input.bounds();
     for (_, mat) in materials.iter_mut() {
         mat.xy_bounds = bounds;
+        mat.xy_bounds_lo = bounds_lo;
         mat.time = mat.time.wrapping_add((t.delta().as_nanos()) as i32);
     }
     for (_, mat) in materials2.iter_mut() {
         mat.xy_bounds = bounds;
+        mat.xy_bounds_lo = bounds_lo;
+        mat.bits = input.input.bits();
         mat.time = mat.time.wrapping_add((t.delta().as_nanos()) as i32);
     }
 
     for mut points in q_points.iter_mut() {
-        points.init = input.max_len;
+        points.init = input.uploaded.min(input.x_vec.len() as u32);
+        points.edge_len = input.edge_len;
+    }
+}
+
+/// Replace the random samples by every point of the region, when there are few enough
+pub fn enumerate_region(
+    mut points: ResMut<InputPoints>,
+) {
+    let points = &mut *points;
+    let region = (points.x_range, points.y_range, points.input);
+    if !points.exhaustive {
+        points.enumerated = None;
+        return;
+    }
+    if points.enumerated == Some(region) { return }
+
+    let nx = points.axis_len(points.x_range);
+    let ny = points.axis_len(points.y_range);
+    if nx * ny > InputPoints::MAX_LEN as u128 {
+        points.enumerated = None;
+        return;
+    }
+
+    let mut x_vec = Vec::with_capacity((nx * ny) as usize);
+    let mut y_vec = Vec::with_capacity((nx * ny) as usize);
+    for j in 0..ny as u64 {
+        let y = points.axis_point(points.y_range, j);
+        for i in 0..nx as u64 {
+            x_vec.push(points.axis_point(points.x_range, i));
+            y_vec.push(y);
+        }
+    }
+    points.modified = (0..x_vec.len() as u32).collect();
+    points.x_vec = x_vec;
+    points.y_vec = y_vec;
+    points.enumerated = Some(region);
+}
+
+/// Keep the edge cases of the region, crossed in x and y, in the first samples
+///
+/// The samples that held edge cases no longer in the region are drawn again.
+pub fn place_edge_cases(
+    mut points: ResMut<InputPoints>,
+) {
+    let points = &mut *points;
+    let old_len = points.edge_len as usize;
+    let mut len = 0;
+    if points.edge_cases && points.enumerated.is_none() {
+        let values = sampling::edge_cases(points.input);
+        let xs: Vec<i64> = values.into_iter().filter(|x| points.x_range.contains(x)).collect();
+        let ys: Vec<i64> = if points.one_dim {
+            vec![points.y_range.start]
+        } else {
+            values.into_iter().filter(|y| points.y_range.contains(y)).collect()
+        };
+        for (i, (x, y)) in ys.iter().flat_map(|&y| xs.iter().map(move |&x| (x, y))).enumerate() {
+            if i == points.x_vec.len() {
+                points.x_vec.push(x);
+                points.y_vec.push(y);
+            } else if (points.x_vec[i], points.y_vec[i]) == (x, y) {
+                continue;
+            } else {
+                points.x_vec[i] = x;
+                points.y_vec[i] = y;
+            }
+            points.modified.insert(i as u32);
+        }
+        len = xs.len() * ys.len();
+    }
+    for i in len..old_len.min(points.x_vec.len()) {
+        let (dist, input) = (points.distribution, points.input);
+        points.x_vec[i] = dist.sample(&mut points.rng, points.x_range, input);
+        points.y_vec[i] = dist.sample(&mut points.rng, points.y_range, input);
+        points.modified.insert(i as u32);
     }
+    points.edge_len = len as u32;
 }
 
 #[derive(Event)]
 pub struct Spawn(
     pub u32,
-    pub LinearRgba,
+    pub FuncDesc,
 );
 
 
@@ -155,21 +605,22 @@ pub fn spawn_points(
     mut materials: ResMut<Assets<ParticleMaterial>>,
     mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
 ) {
-    let mut zs = ShaderStorageBuffer::with_size(inputs.max_len as usize * mem::size_of::<f32>(), RenderAssetUsages::RENDER_WORLD);
-    zs.set_data(vec![0.0; inputs.max_len as usize]);
+    let mut zs = ShaderStorageBuffer::with_size(InputPoints::MAX_LEN as usize * mem::size_of::<f32>(), RenderAssetUsages::RENDER_WORLD);
+    zs.set_data(vec![0.0; InputPoints::MAX_LEN as usize]);
     zs.buffer_description.usage |= BufferUsages::VERTEX;
     let zs = buffers.add(zs);
 
+    let Spawn(id, ref desc) = *trigger.event();
+    let [r, g, b] = desc.color;
+    let (xy_bounds, xy_bounds_lo) = inputs.bounds();
     let mat = materials.add(ParticleMaterial {
         time: 0,
-        color: trigger.1,
-        xy_bounds: [
-            inputs.x_range.start,
-            inputs.y_range.start,
-            inputs.x_range.end,
-            inputs.y_range.end,
-        ].into(),
+        color: LinearRgba::new(r, g, b, 0.0),
+        xy_bounds,
+        xy_bounds_lo,
         z_scale: 1.0,
+        highlight: ParticleMaterial::NO_HIGHLIGHT,
+        mismatches: (desc.kind == Kind::Comparison) as u32,
     });
 
     commands.spawn((
@@ -177,12 +628,18 @@ pub fn spawn_points(
             xs: inputs.xs.clone(),
             ys: inputs.ys.clone(),
             zs: zs.clone(),
-            init: inputs.max_len, // FIXME
+            init: inputs.uploaded.min(inputs.x_vec.len() as u32),
+            edge_len: inputs.edge_len,
         },
         MeshMaterial3d(mat),
         FuncXY {
-            id: trigger.0,
+            id,
+            desc: desc.clone(),
             zs,
+            values: vec![],
+            stats: None,
+            nan: NanMode::default(),
+            timing_mode: TimingMode::default(),
         },
         Visibility::default(),
         Transform::default(),
@@ -195,11 +652,23 @@ pub struct ParticleMaterial {
     #[uniform(0)]
     time: i32,
     #[uniform(1)]
-    color: LinearRgba,
+    pub color: LinearRgba,
     #[uniform(2)]
     xy_bounds: IVec4,
     #[uniform(3)]
-    z_scale: f32,
+    pub z_scale: f32,
+    #[uniform(4)]
+    xy_bounds_lo: UVec4,
+    /// Values in this range are highlighted
+    #[uniform(5)]
+    pub highlight: Vec2,
+    /// Whether nonzero values are highlighted instead, for comparisons
+    #[uniform(6)]
+    pub mismatches: u32,
+}
+
+impl ParticleMaterial {
+    pub const NO_HIGHLIGHT: Vec2 = Vec2::new(1.0, 0.0);
 }
 
 impl Widget for &mut ParticleMaterial {
@@ -219,31 +688,26 @@ impl Widget for &mut ParticleMaterial {
 impl Material for ParticleMaterial {}
 
 
-fn range_uniform(r: RangeInclusive<i32>) -> Uniform<i32> {
-    Uniform::new_inclusive(r.start, r.end)
-}
-
 fn change_region(
     trigger: Trigger<RegionUpdate>,
     mut points: ResMut<InputPoints>,
 ) {
     let points: &mut InputPoints = &mut points;
-    let (new, old, other, vec) = match *trigger.event() {
-        RegionUpdate::StartX(n) => (n, &mut points.x_range.start, points.x_range.end, &mut points.x_vec),
-        RegionUpdate::StartY(n) => (n, &mut points.y_range.start, points.y_range.end, &mut points.y_vec),
-        RegionUpdate::EndX(n) =>   (n, &mut points.x_range.end, points.x_range.start, &mut points.x_vec),
-        RegionUpdate::EndY(n) =>   (n, &mut points.y_range.end, points.y_range.start, &mut points.y_vec),
+    let (range, vec, n, set_start) = match *trigger.event() {
+        RegionUpdate::StartX(n) => (&mut points.x_range, &mut points.x_vec, n, true),
+        RegionUpdate::StartY(n) => (&mut points.y_range, &mut points.y_vec, n, true),
+        RegionUpdate::EndX(n) =>   (&mut points.x_range, &mut points.x_vec, n, false),
+        RegionUpdate::EndY(n) =>   (&mut points.y_range, &mut points.y_vec, n, false),
 
         RegionUpdate::SetLen(new) => {
             let new = new.min(points.max_len);
             let old = points.x_vec.len() as u32;
             if new > old {
                 points.modified.extend(old..new);
-                let ref mut rng = StdRng::from_entropy();
-                let x_distr = range_uniform(points.x_range);
-                let y_distr = range_uniform(points.y_range);
-                points.x_vec.extend(rng.sample_iter(x_distr).take((new - old) as usize));
-                points.y_vec.extend(rng.sample_iter(y_distr).take((new - old) as usize));
+                let (rng, dist, input) = (&mut points.rng, points.distribution, points.input);
+                let (x_range, y_range) = (points.x_range, points.y_range);
+                points.x_vec.extend((old..new).map(|_| dist.sample(rng, x_range, input)));
+                points.y_vec.extend((old..new).map(|_| dist.sample(rng, y_range, input)));
             } else if new < old {
                 drop(points.modified.split_off(&new));
                 points.x_vec.truncate(new as usize);
@@ -252,81 +716,11 @@ fn change_region(
             return;
         }
     };
-    let old = mem::replace(old, new);
-
-    let old_len = old.abs_diff(other);
-    let new_len = new.abs_diff(other);
-    let mut rng = StdRng::from_entropy();
-
-    match new_len.cmp(&old_len) {
-        std::cmp::Ordering::Less => {
-            let lb = new.min(other);
-            let ub = new.max(other);
-            let new_distr = Uniform::new_inclusive(lb, ub);
-            // shrinking: invalidate values that are out-of-bounds
-
-            
-            for (idx, val) in vec.iter_mut().enumerate() {
-                if !(lb..=ub).contains(val) {
-                    *val = new_distr.sample(&mut rng);
-                    points.modified.insert(idx as u32);
-                }
-            }
-        }
-        std::cmp::Ordering::Equal => (),
-        std::cmp::Ordering::Greater => {
-            // growing: pick a random subset of the computed values and give them new values
-            let p = (new_len - old_len) as f32 / (new_len as f32 + 1.0);
-
-            let new_range = Uniform::new_inclusive(
-                new.min(old + 1),
-                new.max(old - 1),
-            );
-
-
-            // Let P("choose the ith item") = p  (for all i)
-            // P([1, ..]) = p (choosing the first element)
-            // P([0, 1, ..]) = p(1-p) (the second element is the first chosen one)
-            // P([0, 0, 1, ..]) = p(1-p)^2 (the third element is the first chosen one)
-            // This is the geometric distribution:
-            // P("first choice is arr[k]") = p*(1-p)^k
-            // P("the first choice is in arr[k..]") = (1-p)^k
-
-            // consider the sequence
-            // p(k) = (1-p)^k
-            // For all k >= 0,
-            //  - 1 >= p(k) > p(k+1) > 0
-            //  - P("first choice is arr[k]") = p(k) - p(k+1)
-
-            // Given a uniform X in [0,1)
-
-            // P("first choice is arr[k]")
-            //  = P( p(k) >= 1-X > p(k+1) )
-            //  = P( (1-p)^k >= 1-X > (1-p)^(k+1) )
-            //  = P( k*log(1-p) >= log(1-X) > (k+1)*log(1-p) )
-            //  = P( k <= log(1-X)/log(1-p) < k+1 )
-
-            assert!(p > 0.0);
-            assert!(p <= 1.0);
-            
-            const EPS: f32 = 1.0 / (1u64 << 32) as f32;
-            let ln1mp = f32::ln_1p(- p);
-            let rln1mp = 1.0 / ln1mp;
-            let mut idx = 0;
-            loop {
-                let x: f32 = rng.r#gen::<u32>() as f32 * EPS;
-                // saturating cast to u32 first
-                idx += (f32::ln_1p(- x) * rln1mp) as u32 as usize;
-                //info!("x = {x}, ln(1-x) = {}, ln(1-x)*rln1mp = {}, rln1mp = {rln1mp}", f32::ln_1p(- x),  f32::ln_1p(- x) * rln1mp);
-
-                if let Some(val) = vec.get_mut(idx) {
-                    *val = new_range.sample(&mut rng);
-                    points.modified.insert(idx as u32);
-                    idx += 1;
-                } else {
-                    break;
-                }
-            }
-        }
-    }
+    // moving one end past the other one moves both
+    let old = *range;
+    let new = if set_start { sampling::with_start(old, n) } else { sampling::with_end(old, n) };
+    *range = new;
+
+    let modified = &mut points.modified;
+    points.distribution.update(vec, old, new, points.input, &mut points.rng, |idx| { modified.insert(idx as u32); });
 }
@@ -0,0 +1,124 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::func_xy::{FuncXY, InputPoints, ParticleMaterial};
+
+/// Histograms of the values of functions, opened from the list of functions
+pub fn plugin(app: &mut App) {
+    app.add_systems(Update, histogram_windows);
+}
+
+/// An open histogram window
+#[derive(Component)]
+pub struct HistogramView {
+    bins: usize,
+    log: bool,
+    // the values of the clicked bin, highlighted in the point cloud
+    selected: Option<(f32, f32)>,
+}
+
+impl Default for HistogramView {
+    fn default() -> Self {
+        Self {
+            bins: 64,
+            log: false,
+            selected: None,
+        }
+    }
+}
+
+// Counts of the finite values in `bins` equal parts of [lo, hi]
+//
+// The bins are computed in `f64`, in which the width of any range of `f32` is finite.
+fn histogram(values: impl Iterator<Item = f32> + Clone, bins: usize) -> Option<(f32, f32, Vec<u32>)> {
+    let finite = values.filter(|v| v.is_finite());
+    let lo = finite.clone().min_by(f32::total_cmp)?;
+    let hi = finite.clone().max_by(f32::total_cmp)?;
+    let mut counts = vec![0; bins];
+    let scale = bins as f64 / (hi as f64 - lo as f64);
+    for v in finite {
+        let k = if hi > lo { ((v as f64 - lo as f64) * scale) as usize } else { 0 };
+        counts[k.min(bins - 1)] += 1;
+    }
+    Some((lo, hi, counts))
+}
+
+fn histogram_windows(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    points: Res<InputPoints>,
+    mut q_hist: Query<(Entity, &FuncXY, &MeshMaterial3d<ParticleMaterial>, &mut HistogramView)>,
+    mut materials: ResMut<Assets<ParticleMaterial>>,
+) {
+    let ctx = contexts.ctx_mut();
+    for (entity, f, mat, mut view) in &mut q_hist {
+        let view = &mut *view;
+        let mut open = true;
+        let len = points.x_vec.len().min(f.values.len());
+
+        egui::Window::new(format!("Histogram: {}", f.desc.name))
+            .id(egui::Id::new(("histogram", entity)))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.add(egui::Slider::new(&mut view.bins, 4..=512).logarithmic(true).text("bins"));
+                    ui.checkbox(&mut view.log, "log counts");
+                });
+                let values = f.values[..len].iter().copied();
+                let Some((lo, hi, counts)) = histogram(values, view.bins) else {
+                    ui.label("no finite values");
+                    return;
+                };
+
+                let size = egui::vec2(ui.available_width().max(320.0), 160.0);
+                let (response, painter) = ui.allocate_painter(size, egui::Sense::click());
+                let rect = response.rect;
+                let width = (hi as f64 - lo as f64) / view.bins as f64;
+                let edge = |k: usize| (lo as f64 + k as f64 * width) as f32;
+                let bin_range = |k: usize| (edge(k), edge(k + 1));
+                let bin_at = |x: f32| (((x - rect.left()) / rect.width() * view.bins as f32) as usize).min(view.bins - 1);
+
+                let height = |c: u32| if view.log { (c as f32).ln_1p() } else { c as f32 };
+                let max = counts.iter().copied().map(height).fold(0.0, f32::max).max(1.0);
+                let [r, g, b] = materials.get(mat).unwrap().color.to_f32_array_no_alpha();
+                let color: egui::Color32 = egui::Rgba::from_rgb(r, g, b).into();
+                for (k, &c) in counts.iter().enumerate() {
+                    let (a, b) = bin_range(k);
+                    let selected = view.selected.is_some_and(|(s0, s1)| a <= s1 && s0 <= b);
+                    let x0 = rect.left() + rect.width() * k as f32 / view.bins as f32;
+                    let x1 = rect.left() + rect.width() * (k + 1) as f32 / view.bins as f32;
+                    let y0 = rect.bottom() - rect.height() * height(c) / max;
+                    let bar = egui::Rect::from_min_max(egui::pos2(x0, y0), egui::pos2(x1, rect.bottom()));
+                    painter.rect_filled(bar, 0.0, if selected { egui::Color32::WHITE } else { color });
+                }
+
+                if let Some(p) = response.hover_pos() {
+                    let k = bin_at(p.x);
+                    let (a, b) = bin_range(k);
+                    response.clone().on_hover_text_at_pointer(format!("[{a:e}, {b:e}): {}", counts[k]));
+                }
+                if response.clicked()
+                    && let Some(p) = response.interact_pointer_pos()
+                {
+                    let bin = bin_range(bin_at(p.x));
+                    view.selected = if view.selected == Some(bin) { None } else { Some(bin) };
+                }
+                ui.horizontal(|ui| {
+                    ui.monospace(format!("{lo:e}"));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.monospace(format!("{hi:e}"));
+                    });
+                });
+                if view.selected.is_some() && ui.button("clear selection").clicked() {
+                    view.selected = None;
+                }
+            });
+
+        if !open {
+            view.selected = None;
+            commands.entity(entity).remove::<HistogramView>();
+        }
+        let highlight = view.selected.map_or(ParticleMaterial::NO_HIGHLIGHT, |(a, b)| Vec2::new(a, b));
+        materials.get_mut(mat).unwrap().highlight = highlight;
+    }
+}
@@ -1,5 +1,5 @@
 
-use std::{ops::Bound, panic::{AssertUnwindSafe, BacktraceStyle}};
+use std::panic::{AssertUnwindSafe, BacktraceStyle};
 
 use bevy::{
     prelude::*,
@@ -14,8 +14,11 @@ use bevy::{
     },
 };
 use binding_types::uniform_buffer;
+use bevy_egui::egui::{self, Widget};
 
-use crate::{func_xy::{FuncXY, InputPoints, ParticleMaterial}, PANIC_INFO};
+use lib::{Estimator, InputType, Kind, NanMode, Timer, Timing, TimingMode, MAX_REPETITIONS};
+
+use crate::{adaptive::Adaptive, func_xy::{hi, FuncXY, InputPoints, ParticleMaterial}, worker::{Batch, Done, Worker}, PANIC_INFO};
 
 /// This example uses a shader source file from the assets subdirectory
 const SHADER_ASSET_PATH: &str = "shaders/updater.wgsl";
@@ -30,9 +33,11 @@ pub struct GpuReadbackPlugin;
 #[derive(Resource, ExtractResource, Clone, Default)]
 pub struct UpdateBuffer {
     idxs: Handle<ShaderStorageBuffer>,
+    // (source, destination, 32-bit words per element)
     updates: Vec<(
         Handle<ShaderStorageBuffer>,
         Handle<ShaderStorageBuffer>,
+        u32,
     )>,
     len: u32,
 }
@@ -49,6 +54,33 @@ pub struct UpdateUniforms {
 }
 
 
+/// How the timing functions measure, chosen from the UI
+#[derive(Resource, Default)]
+pub struct TimingConfig(pub Timing);
+
+impl Widget for &mut TimingConfig {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        ui.collapsing("Timing", |ui| {
+            egui::ComboBox::from_label("timer")
+                .selected_text(self.0.timer.name())
+                .show_ui(ui, |ui| {
+                    for t in Timer::ALL {
+                        ui.selectable_value(&mut self.0.timer, t, t.name());
+                    }
+                });
+            ui.label("time-stamp counter: reference cycles, instant: ns, perf_event: core cycles");
+            ui.add(egui::Slider::new(&mut self.0.repetitions, 1..=MAX_REPETITIONS).text("repetitions"));
+            egui::ComboBox::from_label("estimator")
+                .selected_text(self.0.estimator.name())
+                .show_ui(ui, |ui| {
+                    for e in Estimator::ALL {
+                        ui.selectable_value(&mut self.0.estimator, e, e.name());
+                    }
+                });
+        }).header_response
+    }
+}
+
 #[derive(Resource)]
 struct GpuBufferBindGroup(Vec<BindGroup>);
 #[derive(Resource, ExtractResource, Clone)]
@@ -73,6 +105,8 @@ impl Plugin for GpuReadbackPlugin {
         app.add_plugins(ExtractResourcePlugin::<ParticleMaterial>::default());
         app.add_systems(Update, periodic_updates);
         app.init_resource::<UpdateBuffer>();
+        app.init_resource::<Adaptive>();
+        app.init_resource::<TimingConfig>();
         
         let render_app = app.sub_app_mut(RenderApp);
         render_app.add_systems(Render,prepare_bind_group.in_set(RenderSet::PrepareBindGroups));
@@ -96,7 +130,7 @@ impl FromWorld for ComputePipeline {
             &BindGroupLayoutEntries::sequential(
                 ShaderStages::COMPUTE,
                 (
-                    uniform_buffer::<u32>(false),
+                    uniform_buffer::<UVec2>(false),
                     storage_buffer::<Vec<u32>>(false),
                     storage_buffer::<Vec<f32>>(false),
                     storage_buffer::<Vec<f32>>(false),
@@ -119,44 +153,63 @@ impl FromWorld for ComputePipeline {
 }
 
 
-// FIXME: under some conditions (TBD), the set of points can become non-uniform
 fn periodic_updates(
     mut updates: ResMut<UpdateBuffer>,
     mut inputs: ResMut<InputPoints>,
+    mut adaptive: ResMut<Adaptive>,
+    timing: Res<TimingConfig>,
+    mut worker: Local<Worker>,
     mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
-    mut q_func: Query<&FuncXY>,
+    mut q_func: Query<(Entity, &mut FuncXY)>,
 ) {
     let inputs = &mut *inputs;
     updates.updates.clear();
+    upload_results(&mut updates, inputs, &mut adaptive, &mut worker, &mut buffers, &mut q_func);
+
+    // the next batch is sent once the worker is done with the last
+    if worker.busy() { return }
 
     use rand::Rng;
-    let mut rng = rand::thread_rng();
-    let len = inputs.refresh_rate;
+    // the noises are given by evaluating their timings
+    let noises: std::collections::HashMap<u32, Entity> = q_func.iter()
+        .filter(|(_, func)| func.desc.kind == Kind::Noise)
+        .map(|(entity, func)| (func.id, entity))
+        .collect();
+    let calls: Vec<_> = q_func.iter()
+        .filter(|(_, func)| func.desc.arity == inputs.arity() && func.desc.kind != Kind::Noise)
+        .map(|(entity, func)| (entity, func.call(), func.desc.noise.and_then(|id| noises.get(&id).copied())))
+        .collect();
+    // the timings run alone on the worker thread, so batches with any of them stay small
+    let len = if calls.iter().any(|(_, call, _)| call.kind == Kind::Timing) {
+        inputs.refresh_rate
+    } else {
+        inputs.refresh_rate.saturating_mul(worker.threads() as u32)
+    };
 
     let mut idxs: Vec<u32> = Vec::with_capacity(len as _);
     let mut idx_map = std::collections::HashMap::new();
-    let mut xs: Vec<i32> = Vec::with_capacity(len as _);
-    let mut ys: Vec<i32> = Vec::with_capacity(len as _);
-
+    let mut xs: Vec<i64> = Vec::with_capacity(len as _);
+    let mut ys: Vec<i64> = Vec::with_capacity(len as _);
 
-    let mut modified = inputs.modified.lower_bound_mut(Bound::Unbounded);
 
     for _ in 0..len {
-        let idx = match modified.remove_next() {
+        let idx = match inputs.modified.pop_first() {
             Some(i) => i,
             None => {
-                if !inputs.refresh_random { break }
+                if !inputs.refresh_random || inputs.enumerated.is_some() { break }
                 let mut i = inputs.x_vec.len() as u32;
 
                 if i < inputs.max_len {
                     // placeholders
-                    inputs.x_vec.push(i32::MAX);
-                    inputs.y_vec.push(i32::MAX);
+                    inputs.x_vec.push(i64::MAX);
+                    inputs.y_vec.push(i64::MAX);
                 } else {
-                    i = rng.r#gen::<u32>() % inputs.max_len;
+                    // the edge cases stay in place
+                    i = inputs.edge_len + inputs.rng.r#gen::<u32>() % (inputs.max_len - inputs.edge_len);
                 }
-                inputs.x_vec[i as usize] = rng.gen_range(inputs.x_range.start..=inputs.x_range.end);
-                inputs.y_vec[i as usize] = rng.gen_range(inputs.y_range.start..=inputs.y_range.end);
+                let (x, y) = adaptive.sample(inputs, i);
+                inputs.x_vec[i as usize] = x;
+                inputs.y_vec[i as usize] = y;
                 i
             }
         };
@@ -177,47 +230,122 @@ fn periodic_updates(
         }
     }
 
-    let len = idxs.len() as u32;
+    if idxs.is_empty() { return }
+    inputs.pending = idxs.iter().copied().collect();
+    worker.send(Batch { idxs, xs, ys, timing: timing.0, calls });
+}
+
+// Store and upload the results of the batch the worker is done with, except for
+// the points that have been moved and the functions whose id, NaN mode or timing
+// mode has changed since it was sent; the noise of a timing is stored with it
+//
+// The positions of new samples are uploaded with their results, until which they are not drawn.
+fn upload_results(
+    updates: &mut UpdateBuffer,
+    inputs: &mut InputPoints,
+    adaptive: &mut Adaptive,
+    worker: &mut Worker,
+    buffers: &mut Assets<ShaderStorageBuffer>,
+    q_func: &mut Query<(Entity, &mut FuncXY)>,
+) {
+    // the indices of the last batch stay, unused while `len` is 0
+    updates.len = 0;
+    inputs.uploaded = inputs.uploaded.min(inputs.x_vec.len() as u32);
+    let Some(Done { batch, results }) = worker.try_recv() else { return };
+    inputs.pending.clear();
+
+    let keep: Vec<usize> = (0..batch.idxs.len()).filter(|&k| {
+        let i = batch.idxs[k] as usize;
+        inputs.x_vec.get(i) == Some(&batch.xs[k]) && inputs.y_vec.get(i) == Some(&batch.ys[k])
+    }).collect();
+    if keep.is_empty() { return }
+    fn pick<T: Copy>(v: &[T], keep: &[usize]) -> Vec<T> {
+        keep.iter().map(|&k| v[k]).collect()
+    }
+    let idxs = pick(&batch.idxs, &keep);
+    updates.len = idxs.len() as u32;
+    updates.idxs = buffers.add(ShaderStorageBuffer::from(idxs.clone()));
+    inputs.uploaded = idxs.iter().fold(inputs.uploaded, |n, &i| n.max(i + 1));
+
+    let mut upload = |func: &mut FuncXY, r: &[f32]| {
+        let r = pick(r, &keep);
+        func.store(&idxs, &r, inputs.x_vec.len());
+        if adaptive.target.as_ref() == Some(&func.desc.name) {
+            adaptive.observe(inputs, &idxs, &func.values);
+        }
+        updates.updates.push((
+            buffers.add(ShaderStorageBuffer::from(r)),
+            func.zs.clone(),
+            1,
+        ));
+    };
+    for ((entity, call, noise), (r, spread)) in batch.calls.iter().zip(results) {
+        let Ok((_, mut func)) = q_func.get_mut(*entity) else { continue };
+        if (func.id, func.nan, func.timing_mode) != (call.id, call.nan, call.timing_mode) { continue }
+        if func.desc.arity != inputs.arity() { continue }
+        upload(&mut func, &r);
+        let id = func.desc.noise;
+        if let Some(noise) = noise && let Ok((_, mut noise)) = q_func.get_mut(*noise) && Some(noise.id) == id {
+            upload(&mut noise, &spread);
+        }
+    }
 
-    let idxs = buffers.add(ShaderStorageBuffer::from(idxs));
-    updates.len = len;
-    updates.idxs = idxs;
+    let xs = buffers.add(ShaderStorageBuffer::from(words(&pick(&batch.xs, &keep))));
+    let ys = buffers.add(ShaderStorageBuffer::from(words(&pick(&batch.ys, &keep))));
+    updates.updates.push((xs, inputs.xs.clone(), 2));
+    updates.updates.push((ys, inputs.ys.clone(), 2));
+}
 
-    let mut r = vec![0.0; xs.len()];
+/// What `evaluate` needs of a `FuncXY`, to be sent to the worker thread
+#[derive(Clone, Debug)]
+pub struct Call {
+    pub id: u32,
+    pub kind: Kind,
+    pub input: InputType,
+    pub nan: NanMode,
+    pub timing_mode: TimingMode,
+}
 
+/// Evaluate a function with the hot-reloaded lib at the given points
+///
+/// Unless `spread` is empty, it receives the noise of a timing. A panic is logged
+/// as a warning, leaving `r` and `spread` partially written.
+pub fn evaluate(func: &Call, timing: Timing, xs: &[i64], ys: &[i64], r: &mut [f32], spread: &mut [f32]) -> bool {
+    let id = func.id;
+    let timing = Timing { mode: func.timing_mode, ..timing };
     PANIC_INFO.set(None);
-    for FuncXY { id, zs } in &mut q_func {
-        let rs = AssertUnwindSafe(r.as_mut_slice());
-        if let Err(e) = std::panic::catch_unwind(|| {
-            let rs = rs;
-            crate::hot::test_batched(&xs, &ys, rs.0, *id)
-        }) {
-            if let Some((panic, bt)) = PANIC_INFO.replace(None) {
-                let style = std::panic::get_backtrace_style();
-                let mut bt_str = "Backtrace:".to_owned();
-                match (&bt, style) {
-                    (None, Some(BacktraceStyle::Off)) => bt_str.push_str(" disabled"),
-                    (None, None) => bt_str.push_str(" unsupported"),
-                    (Some(bt), Some(BacktraceStyle::Full)) => bt_str.push_str(&format!("\n{bt:#}")),
-                    (Some(bt), Some(BacktraceStyle::Short)) => bt_str.push_str(&format!("\n{bt:}")),
-                    _ => unreachable!(),
-                };
-                warn!("Panic in hot-reloaded code:\n{panic}\n{bt_str}\n");
-            } else {
-                warn!("Caught unwind but panic hook has not been run!");
-            }
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| match (func.kind, func.input) {
+        (Kind::Comparison, _) => crate::hot::compare_batched(xs, ys, r, id, func.nan),
+        (_, InputType::F32) => {
+            let xs32: Vec<i32> = xs.iter().copied().map(hi).collect();
+            let ys32: Vec<i32> = ys.iter().copied().map(hi).collect();
+            crate::hot::test_batched(&xs32, &ys32, r, spread, id, timing)
+        }
+        (_, InputType::F64) => crate::hot::test_batched64(xs, ys, r, spread, id, timing),
+    }));
+    if result.is_err() {
+        if let Some((panic, bt)) = PANIC_INFO.replace(None) {
+            let style = std::panic::get_backtrace_style();
+            let mut bt_str = "Backtrace:".to_owned();
+            match (&bt, style) {
+                (None, Some(BacktraceStyle::Off)) => bt_str.push_str(" disabled"),
+                (None, None) => bt_str.push_str(" unsupported"),
+                (Some(bt), Some(BacktraceStyle::Full)) => bt_str.push_str(&format!("\n{bt:#}")),
+                (Some(bt), Some(BacktraceStyle::Short)) => bt_str.push_str(&format!("\n{bt:}")),
+                _ => unreachable!(),
+            };
+            warn!("Panic in hot-reloaded code:\n{panic}\n{bt_str}\n");
+        } else {
+            warn!("Caught unwind but panic hook has not been run!");
         }
-        updates.updates.push((
-            buffers.add(ShaderStorageBuffer::from(r.clone())),
-            zs.clone()
-        ));
     }
     PANIC_INFO.set(Some((String::new(), None)));
+    result.is_ok()
+}
 
-    let xs = buffers.add(ShaderStorageBuffer::from(&xs));
-    let ys = buffers.add(ShaderStorageBuffer::from(&ys));
-    updates.updates.push((xs, inputs.xs.clone()));
-    updates.updates.push((ys, inputs.ys.clone()));
+// 64-bit coordinates as (low, high) words, matching the vertex layout
+fn words(v: &[i64]) -> Vec<UVec2> {
+    v.iter().map(|&x| UVec2::new(x as u32, (x >> 32) as u32)).collect()
 }
 
 
@@ -229,12 +357,13 @@ fn prepare_bind_group(
     mut updates: ResMut<UpdateBuffer>,
     buffers: Res<RenderAssets<GpuShaderStorageBuffer>>,
 ) {
+    if updates.updates.is_empty() { return }
     let mut bind_groups = vec![];
     let idxs = buffers.get(&updates.idxs).unwrap();
-    let mut uniform = UniformBuffer::from(updates.len);
-    uniform.write_buffer(&render_device, &render_queue);
 
-    for (src, dst) in &updates.updates {
+    for &(ref src, ref dst, words) in &updates.updates {
+        let mut uniform = UniformBuffer::from(UVec2::new(updates.len, words));
+        uniform.write_buffer(&render_device, &render_queue);
         let src = buffers.get(src).unwrap();
         let dst = buffers.get(dst).unwrap();
         let bind_group = render_device.create_bind_group(
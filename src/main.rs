@@ -1,5 +1,4 @@
 #![feature(new_range_api)]
-#![feature(btree_cursors)]
 #![feature(panic_update_hook)]
 #![feature(panic_backtrace_config)]
 #![feature(backtrace_frames)]
@@ -18,6 +17,15 @@ mod orbit_cam;
 mod lines;
 mod func_xy;
 mod point_cloud;
+mod pick;
+mod zoom;
+mod export;
+mod session;
+mod stats;
+mod histogram;
+mod adaptive;
+mod sampling;
+mod worker;
 
 #[derive(Component)]
 pub struct PanningCamera;
@@ -61,6 +69,11 @@ fn main() {
             incremental::GpuReadbackPlugin,
             func_xy::PluginXY,
             plane::plugin,
+            pick::plugin,
+            zoom::plugin,
+            export::plugin,
+            session::plugin,
+            histogram::plugin,
             bevy_egui::EguiPlugin,
             PointCloudPipelinePlugin,
         ))
@@ -101,5 +114,10 @@ thread_local! {
 
 #[hot_lib_reloader::hot_module(dylib = "lib", file_watch_debounce = 50)]
 mod hot {
+    use lib::{FuncDesc, NanMode, Timing};
+
     hot_functions_from_file!("lib/src/lib.rs");
+
+    #[lib_version]
+    pub fn version() -> usize {}
 }
@@ -43,7 +43,7 @@ pub fn spawn_camera(mut commands: Commands) {
 }
 
 pub fn orbit_camera(
-    _kbd: Res<ButtonInput<KeyCode>>,
+    kbd: Res<ButtonInput<KeyCode>>,
     click: Res<ButtonInput<MouseButton>>,
     mut evr_motion: EventReader<bevy::input::mouse::MouseMotion>,
     mut evr_scroll: EventReader<bevy::input::mouse::MouseWheel>,
@@ -59,7 +59,9 @@ pub fn orbit_camera(
     let mut total_motion = Vec2::ZERO;
     let mut scroll = Vec2::ZERO;
     if get_mouse {
-        if click.pressed(MouseButton::Left) {
+        // shift-dragging selects a region to zoom into instead
+        let shift = kbd.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+        if click.pressed(MouseButton::Left) && !shift {
             for ev in evr_motion.read() {
                 total_motion += ev.delta;
             }
@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use lib::{InputType, Total32, Total64};
+
+use crate::func_xy::{hi, int_map, FuncXY, InputPoints, ParticleMaterial};
+
+/// Right-click to inspect the nearest sampled point on the screen
+pub fn plugin(app: &mut App) {
+    app.init_resource::<Picked>()
+        .add_systems(Update, (pick_point, pick_ui).chain());
+}
+
+#[derive(Resource, Default)]
+pub struct Picked(Option<Pick>);
+
+pub struct Pick {
+    idx: usize,
+    x: i64,
+    y: i64,
+    input: InputType,
+    // the stored results at the picked point, NaN if not evaluated
+    zs: Vec<(String, f32)>,
+}
+
+/// The point of the xy plane under the cursor, in world coordinates
+pub fn plane_point(
+    windows: &Query<&Window>,
+    cams: &Query<(&Camera, &GlobalTransform)>,
+) -> Option<Vec2> {
+    let cursor = windows.iter().find_map(|w| w.cursor_position())?;
+    let (camera, transform) = cams.get_single().ok()?;
+    let ray = camera.viewport_to_world(transform, cursor).ok()?;
+    let d = ray.intersect_plane(Vec3::ZERO, InfinitePlane3d::new(Vec3::Z))?;
+    Some(ray.get_point(d).truncate())
+}
+
+// The samples are matched by where they are drawn on the screen, with the value of
+// each visible function as their height
+#[allow(clippy::too_many_arguments)]
+fn pick_point(
+    click: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    cams: Query<(&Camera, &GlobalTransform)>,
+    egui_contexts: Query<&bevy_egui::EguiContext>,
+    points: Res<InputPoints>,
+    q_func: Query<(&FuncXY, &MeshMaterial3d<ParticleMaterial>, &Visibility)>,
+    materials: Res<Assets<ParticleMaterial>>,
+    mut picked: ResMut<Picked>,
+) {
+    if !click.just_pressed(MouseButton::Right) { return }
+    if egui_contexts.iter().any(|ctx| ctx.get().wants_pointer_input()) { return }
+    let Some(cursor) = windows.iter().find_map(|w| w.cursor_position()) else { return };
+    let Ok((camera, transform)) = cams.get_single() else { return };
+
+    let mut nearest: Option<(usize, f32)> = None;
+    for (f, mat, visibility) in &q_func {
+        if f.desc.arity != points.arity() || *visibility == Visibility::Hidden { continue }
+        let Some(mat) = materials.get(mat) else { continue };
+        // only the samples whose positions have been uploaded are drawn
+        let drawn = points.x_vec.iter().zip(&points.y_vec).take(points.uploaded as usize).enumerate();
+        for (idx, (&x, &y)) in drawn {
+            if !points.evaluated(idx as u32) { continue }
+            let z = f.value(idx) * mat.z_scale;
+            if !z.is_finite() { continue }
+            let p = Vec3::new(int_map(x, points.x_range), int_map(y, points.y_range), z);
+            let Ok(q) = camera.world_to_viewport(transform, p) else { continue };
+            let d = q.distance_squared(cursor);
+            if nearest.is_none_or(|(_, n)| d < n) {
+                nearest = Some((idx, d));
+            }
+        }
+    }
+    let Some((idx, _)) = nearest else { return };
+
+    let x = points.x_vec[idx];
+    let y = points.y_vec[idx];
+    let zs = q_func.iter()
+        .map(|(f, ..)| f)
+        .filter(|f| f.desc.arity == points.arity())
+        .map(|f| (f.desc.name.clone(), f.value(idx)))
+        .collect();
+
+    picked.0 = Some(Pick { idx, x, y, input: points.input, zs });
+}
+
+// the Total32/Total64 formatting, the value in decimal, and its bits
+fn describe(input: InputType, x: i64) -> [String; 3] {
+    match input {
+        InputType::F32 => {
+            let v = f32::from(Total32(hi(x)));
+            [crate::hot::int_fmt(hi(x)), format!("{v:e}"), format!("0x{:08x}", v.to_bits())]
+        }
+        InputType::F64 => {
+            let v = f64::from(Total64(x));
+            [crate::hot::int_fmt64(x), format!("{v:e}"), format!("0x{:016x}", v.to_bits())]
+        }
+    }
+}
+
+fn pick_ui(
+    mut contexts: EguiContexts,
+    mut picked: ResMut<Picked>,
+) {
+    let Some(pick) = &picked.0 else { return };
+    let mut open = true;
+    egui::Window::new("Picked point").open(&mut open).show(contexts.ctx_mut(), |ui| {
+        ui.label(format!("sample #{}", pick.idx));
+        egui::Grid::new("picked inputs").show(ui, |ui| {
+            for (s, v) in [("x", pick.x), ("y", pick.y)] {
+                ui.label(s);
+                for col in describe(pick.input, v) {
+                    ui.monospace(col);
+                }
+                ui.end_row();
+            }
+        });
+        ui.separator();
+        egui::Grid::new("picked outputs").show(ui, |ui| {
+            for (name, z) in &pick.zs {
+                ui.label(name);
+                ui.monospace(format!("{z:e}"));
+                ui.end_row();
+            }
+        });
+    });
+    if !open {
+        picked.0 = None;
+    }
+}
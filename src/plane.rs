@@ -40,6 +40,8 @@ fn load_assets(
         x: Vec3::X,
         y: Vec3::Y,
         xy_bounds: IVec4::from_array([i32::MIN, i32::MIN, i32::MAX, i32::MAX]),
+        xy_bounds_lo: UVec4::from_array([0, 0, u32::MAX, u32::MAX]),
+        bits: 32,
     });
     commands.spawn((
         Mesh3d(mesh.clone()),
@@ -63,6 +65,11 @@ pub struct PlaneMaterial {
     pub y: Vec3,
     #[uniform(4)]
     pub xy_bounds: IVec4,
+    #[uniform(5)]
+    pub xy_bounds_lo: UVec4,
+    // number of bits shown, counting from the most significant
+    #[uniform(6)]
+    pub bits: u32,
 }
 
 impl Material for PlaneMaterial {
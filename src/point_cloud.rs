@@ -41,6 +41,8 @@ pub struct PointCloudEntity {
     pub ys: Handle<ShaderStorageBuffer>,
     pub zs: Handle<ShaderStorageBuffer>,
     pub init: u32,
+    // the leading points that are edge cases, drawn again as markers
+    pub edge_len: u32,
 }
 
 type DrawPointCloudPipelineCommands = (
@@ -84,6 +86,8 @@ where
         pass.set_vertex_buffer(2, zs);
 
         pass.draw(0..entity.init, 0..1);
+        // the other instances offset the edge cases, drawing a cross around each one
+        pass.draw(0..entity.edge_len.min(entity.init), 1..9);
         RenderCommandResult::Success
     }
 }
@@ -170,24 +174,24 @@ impl SpecializedRenderPipeline for PointCloudPipeline {
                 entry_point: "vertex".into(),
                 buffers: vec![
                     VertexBufferLayout {
-                        array_stride: 4,
+                        array_stride: 8,
                         step_mode: VertexStepMode::Vertex,
                         // this needs to match the layout of Vertex
                         attributes: vec![
                             VertexAttribute {
-                                format: VertexFormat::Sint32,
+                                format: VertexFormat::Uint32x2,
                                 offset: 0,
                                 shader_location: 0,
                             },
                         ],
                     },
                     VertexBufferLayout {
-                        array_stride: 4,
+                        array_stride: 8,
                         step_mode: VertexStepMode::Vertex,
                         // this needs to match the layout of Vertex
                         attributes: vec![
                             VertexAttribute {
-                                format: VertexFormat::Sint32,
+                                format: VertexFormat::Uint32x2,
                                 offset: 0,
                                 shader_location: 1,
                             }, 
@@ -0,0 +1,467 @@
+//! Keeping the samples uniform over the region as it changes
+//!
+//! Each axis is handled on its own: when its range changes from `old` to `new`,
+//! the samples, uniform over `old`, are moved so that they are uniform over `new`.
+//! Other distributions than uniform over the total order of the floats are drawn
+//! again where they would no longer follow the distribution.
+
+use core::range::RangeInclusive;
+use std::f64::consts::FRAC_PI_2;
+
+use lib::{InputType, Total32, Total64};
+use rand::Rng;
+
+/// The number of values in `r`
+pub fn range_len(r: RangeInclusive<i64>) -> u128 {
+    r.end.abs_diff(r.start) as u128 + 1
+}
+
+/// `r` with its start moved to `n`, moving the end along if it would be passed
+pub fn with_start(r: RangeInclusive<i64>, n: i64) -> RangeInclusive<i64> {
+    (n..=r.end.max(n)).into()
+}
+
+/// `r` with its end moved to `n`, moving the start along if it would be passed
+pub fn with_end(r: RangeInclusive<i64>, n: i64) -> RangeInclusive<i64> {
+    (r.start.min(n)..=n).into()
+}
+
+// A uniform value from the union of the disjoint ranges
+fn pick(rng: &mut impl Rng, pieces: &[RangeInclusive<i64>]) -> i64 {
+    let total: u128 = pieces.iter().map(|&r| range_len(r)).sum();
+    let mut k = rng.gen_range(0..total);
+    for &r in pieces {
+        let len = range_len(r);
+        if k < len {
+            return r.start.wrapping_add(k as i64);
+        }
+        k -= len;
+    }
+    unreachable!()
+}
+
+/// Move the samples `v`, uniform over `old`, so that they are uniform over `new`
+///
+/// Samples in the intersection `I` of the ranges are kept with probability
+/// `a = min(1, |old| / |new|)`, and the others are drawn again: from `I` with
+/// probability `w`, and from the rest of `new` otherwise. Then each value of
+/// `I` has probability `a / |old| + (1 - a |I| / |old|) w / |I|`, which is
+/// `1 / |new|` for
+/// ```text
+/// w = |I| (1 / |new| - a / |old|) / (1 - a |I| / |old|)
+/// ```
+/// `modified` is called with the index of each sample that changed.
+pub fn resample(
+    v: &mut [i64],
+    old: RangeInclusive<i64>,
+    new: RangeInclusive<i64>,
+    rng: &mut impl Rng,
+    mut modified: impl FnMut(usize),
+) {
+    if old == new { return }
+
+    let lo = old.start.max(new.start);
+    let hi = old.end.min(new.end);
+    let inter: Option<RangeInclusive<i64>> = (lo <= hi).then(|| (lo..=hi).into());
+
+    // the parts of `new` outside of `old`
+    let mut rest = vec![];
+    match inter {
+        Some(i) => {
+            if new.start < i.start {
+                rest.push((new.start..=i.start - 1).into());
+            }
+            if i.end < new.end {
+                rest.push((i.end + 1..=new.end).into());
+            }
+        }
+        None => rest.push(new),
+    }
+
+    let n_old = range_len(old) as f64;
+    let n_new = range_len(new) as f64;
+    let n_inter = inter.map_or(0.0, |i| range_len(i) as f64);
+    let keep = (n_old / n_new).min(1.0);
+    let w = if rest.is_empty() {
+        1.0
+    } else {
+        (n_inter * (1.0 / n_new - keep / n_old) / (1.0 - keep * n_inter / n_old)).clamp(0.0, 1.0)
+    };
+
+    for (idx, x) in v.iter_mut().enumerate() {
+        let inside = inter.is_some_and(|i| i.contains(x));
+        if inside && rng.gen_bool(keep) { continue }
+        *x = match inter {
+            Some(i) if rest.is_empty() || rng.gen_bool(w) => rng.gen_range(i.start..=i.end),
+            _ => pick(rng, &rest),
+        };
+        modified(idx);
+    }
+}
+
+/// How new samples are spread over an axis of the region
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Distribution {
+    /// Uniform over the total order of the floats, like their bit patterns
+    #[default]
+    TotalOrder,
+    /// Uniform over the finite real values
+    Real,
+    /// Log-uniform in magnitude, over the finite nonzero values
+    LogUniform,
+    /// Within a few ulps of values where functions tend to go wrong, such as
+    /// 0, ±1, multiples of π/2 and the thresholds of overflow
+    NearSpecial,
+}
+
+impl Distribution {
+    pub const ALL: [Self; 4] = [Self::TotalOrder, Self::Real, Self::LogUniform, Self::NearSpecial];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::TotalOrder => "total order",
+            Self::Real => "real",
+            Self::LogUniform => "log-uniform",
+            Self::NearSpecial => "near special values",
+        }
+    }
+
+    /// A sample of `r`, in whole inputs of the selected precision
+    ///
+    /// Falls back to the total order when `r` has none of the values of the distribution.
+    pub fn sample(self, rng: &mut impl Rng, r: RangeInclusive<i64>, input: InputType) -> i64 {
+        let v = match self {
+            Self::TotalOrder => None,
+            Self::Real => finite_values(r, input).map(|(a, b)| {
+                let t: f64 = rng.r#gen();
+                // without the overflow of `b - a`
+                a * (1.0 - t) + b * t
+            }),
+            Self::LogUniform => finite_values(r, input).and_then(|(a, b)| log_uniform(rng, a, b, input)),
+            Self::NearSpecial => return near_special(rng, r, input)
+                .unwrap_or_else(|| rng.gen_range(r.start..=r.end)),
+        };
+        match v {
+            Some(v) => with_low_bits(rng, position(v, input), input).clamp(r.start, r.end),
+            None => rng.gen_range(r.start..=r.end),
+        }
+    }
+
+    /// Move the samples `v`, drawn from `self` over `old`, so that they follow it over `new`
+    ///
+    /// Uniform samples are moved by [`resample`]. For the other distributions, the
+    /// samples are kept when `new` is within `old`, except for those outside of `new`,
+    /// and otherwise they are all drawn again.
+    pub fn update(
+        self,
+        v: &mut [i64],
+        old: RangeInclusive<i64>,
+        new: RangeInclusive<i64>,
+        input: InputType,
+        rng: &mut impl Rng,
+        mut modified: impl FnMut(usize),
+    ) {
+        if self == Self::TotalOrder {
+            return resample(v, old, new, rng, modified);
+        }
+        if old == new { return }
+        let within = old.start <= new.start && new.end <= old.end;
+        for (idx, x) in v.iter_mut().enumerate() {
+            if within && new.contains(x) { continue }
+            *x = self.sample(rng, new, input);
+            modified(idx);
+        }
+    }
+}
+
+// The value at position `x`
+fn value(x: i64, input: InputType) -> f64 {
+    match input {
+        InputType::F32 => f32::from(Total32((x >> 32) as i32)) as f64,
+        InputType::F64 => f64::from(Total64(x)),
+    }
+}
+
+// The first position of `v`, rounded to the selected precision
+fn position(v: f64, input: InputType) -> i64 {
+    match input {
+        InputType::F32 => (Total32::from(v as f32).0 as i64) << 32,
+        InputType::F64 => Total64::from(v).0,
+    }
+}
+
+// Any of the positions with the same value as `x`
+fn with_low_bits(rng: &mut impl Rng, x: i64, input: InputType) -> i64 {
+    match input {
+        InputType::F32 => x | rng.r#gen::<u32>() as i64,
+        InputType::F64 => x,
+    }
+}
+
+fn max_finite(input: InputType) -> f64 {
+    match input {
+        InputType::F32 => f32::MAX as f64,
+        InputType::F64 => f64::MAX,
+    }
+}
+
+// The smallest and largest finite values in `r`, if any
+fn finite_values(r: RangeInclusive<i64>, input: InputType) -> Option<(f64, f64)> {
+    let max = max_finite(input);
+    let lo = r.start.max(position(-max, input));
+    let hi = r.end.min(position(max, input));
+    (lo <= hi).then(|| (value(lo, input), value(hi, input)))
+}
+
+// A value of [a, b] with a log-uniform magnitude, with each sign in proportion to
+// the logarithmic width of its magnitudes
+fn log_uniform(rng: &mut impl Rng, a: f64, b: f64, input: InputType) -> Option<f64> {
+    let tiny = match input {
+        InputType::F32 => f32::from_bits(1) as f64,
+        InputType::F64 => f64::from_bits(1),
+    };
+    // the logarithms of the magnitudes from `lo` to `hi`
+    let side = |lo: f64, hi: f64| (hi >= tiny).then(|| (lo.max(tiny).ln(), hi.ln()));
+    let neg = if a < 0.0 { side((-b).max(0.0), -a) } else { None };
+    let pos = if b > 0.0 { side(a.max(0.0), b) } else { None };
+    let width = |s: Option<(f64, f64)>| s.map_or(0.0, |(l, h)| h - l);
+    let (negative, (l, h)) = match (neg, pos) {
+        (Some(n), None) => (true, n),
+        (None, Some(p)) => (false, p),
+        (Some(n), Some(p)) => {
+            let negative = rng.r#gen::<f64>() * (width(neg) + width(pos)) < width(neg);
+            (negative, if negative { n } else { p })
+        }
+        (None, None) => return None,
+    };
+    let m = (l + (h - l) * rng.r#gen::<f64>()).exp();
+    Some(if negative { -m } else { m })
+}
+
+// The special values at the selected precision
+fn specials(input: InputType) -> [f64; 31] {
+    let (max, min_positive) = match input {
+        InputType::F32 => (f32::MAX as f64, f32::MIN_POSITIVE as f64),
+        InputType::F64 => (f64::MAX, f64::MIN_POSITIVE),
+    };
+    // where `exp` and squaring overflow, and where the subnormals start
+    let thresholds = [max, max.ln(), max.sqrt(), min_positive];
+    let mut v = [0.0; 31];
+    let positive = [0.5, 1.0, 2.0].into_iter()
+        .chain(thresholds)
+        .chain((1..=8).map(|k| k as f64 * FRAC_PI_2));
+    for (k, x) in positive.enumerate() {
+        v[2 * k + 1] = x;
+        v[2 * k + 2] = -x;
+    }
+    v
+}
+
+// A position a few ulps from one of the special values in `r`
+fn near_special(rng: &mut impl Rng, r: RangeInclusive<i64>, input: InputType) -> Option<i64> {
+    let candidates = || specials(input).into_iter()
+        .map(|s| position(s, input))
+        .filter(|p| r.contains(p));
+    let n = candidates().count();
+    if n == 0 { return None }
+    let p = candidates().nth(rng.gen_range(0..n))?;
+    let ulp = 1i64 << (64 - input.bits());
+    let spread = 1i64 << rng.gen_range(0..=16);
+    let x = p.saturating_add(rng.gen_range(-spread..=spread).saturating_mul(ulp));
+    Some(with_low_bits(rng, x, input).clamp(r.start, r.end))
+}
+
+/// The positions of ±0, the smallest subnormals, the smallest normals, the largest
+/// finite values, the infinities, and quiet and signaling NaNs of either sign
+pub fn edge_cases(input: InputType) -> [i64; 14] {
+    let bits: [u64; 7] = match input {
+        InputType::F32 => [0, 1, 0x0080_0000, 0x7f7f_ffff, 0x7f80_0000, 0x7fc0_0000, 0x7fa0_0000],
+        InputType::F64 => [
+            0,
+            1,
+            0x0010_0000_0000_0000,
+            0x7fef_ffff_ffff_ffff,
+            0x7ff0_0000_0000_0000,
+            0x7ff8_0000_0000_0000,
+            0x7ff4_0000_0000_0000,
+        ],
+    };
+    let sign = 1u64 << (input.bits() - 1);
+    let mut v = [0; 14];
+    for (k, b) in bits.into_iter().enumerate() {
+        for (j, b) in [b, b | sign].into_iter().enumerate() {
+            v[2 * k + j] = match input {
+                InputType::F32 => (Total32::from(f32::from_bits(b as u32)).0 as i64) << 32,
+                InputType::F64 => Total64::from(f64::from_bits(b)).0,
+            };
+        }
+    }
+    v.sort_unstable();
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const N: usize = 40_000;
+    const BUCKETS: u128 = 16;
+    // the 0.999 quantile of chi-squared with 15 degrees of freedom
+    const CRITICAL: f64 = 37.697;
+
+    fn range(a: i64, b: i64) -> RangeInclusive<i64> {
+        (a..=b).into()
+    }
+
+    fn uniform(rng: &mut StdRng, r: RangeInclusive<i64>) -> Vec<i64> {
+        (0..N).map(|_| rng.gen_range(r.start..=r.end)).collect()
+    }
+
+    fn chi_squared(v: &[i64], r: RangeInclusive<i64>) -> f64 {
+        let len = range_len(r);
+        let mut counts = [0u64; BUCKETS as usize];
+        for &x in v {
+            assert!(r.contains(&x), "{x} is outside of {r:?}");
+            let k = x.abs_diff(r.start) as u128 * BUCKETS / len;
+            counts[k as usize] += 1;
+        }
+        // bucket k holds the offsets d with floor(d * BUCKETS / len) == k
+        let first = |k: u128| (k * len).div_ceil(BUCKETS);
+        (0..BUCKETS).map(|k| {
+            let e = v.len() as f64 * (first(k + 1) - first(k)) as f64 / len as f64;
+            (counts[k as usize] as f64 - e).powi(2) / e
+        }).sum()
+    }
+
+    fn check(seed: u64, old: RangeInclusive<i64>, new: RangeInclusive<i64>) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut v = uniform(&mut rng, old);
+        resample(&mut v, old, new, &mut rng, |_| ());
+        let chi2 = chi_squared(&v, new);
+        assert!(chi2 < CRITICAL, "{old:?} -> {new:?}: chi-squared {chi2}");
+    }
+
+    #[test]
+    fn grow() {
+        check(1, range(0, 999), range(0, 9_999));
+        check(2, range(0, 999), range(-9_000, 999));
+        check(3, range(0, 999), range(-5_000, 5_000));
+    }
+
+    #[test]
+    fn shrink() {
+        check(4, range(0, 9_999), range(0, 999));
+        check(5, range(0, 9_999), range(3_000, 3_999));
+    }
+
+    #[test]
+    fn overlap() {
+        check(6, range(0, 999), range(500, 10_499));
+        check(7, range(500, 10_499), range(0, 999));
+        check(8, range(0, 999), range(-100, 899));
+    }
+
+    #[test]
+    fn disjoint() {
+        check(9, range(0, 999), range(5_000, 5_999));
+        check(10, range(0, 99), range(-1_000_000, -1));
+    }
+
+    #[test]
+    fn full_range() {
+        check(11, range(i64::MIN, i64::MAX), range(-1_000, 1_000));
+        check(12, range(-1_000, 1_000), range(i64::MIN, i64::MAX));
+        check(13, range(i64::MIN, 0), range(-1, i64::MAX));
+    }
+
+    #[test]
+    fn unchanged_samples_are_not_modified() {
+        let mut rng = StdRng::seed_from_u64(14);
+        let mut v = uniform(&mut rng, range(0, 999));
+        let before = v.clone();
+        let mut modified = vec![];
+        resample(&mut v, range(0, 999), range(0, 1_999), &mut rng, |i| modified.push(i));
+        for (i, (a, b)) in before.iter().zip(&v).enumerate() {
+            assert_eq!(a != b, modified.binary_search(&i).is_ok());
+        }
+        // about half of them move to the new half
+        assert!((N / 3..2 * N / 3).contains(&modified.len()));
+    }
+
+    #[test]
+    fn moving_past_the_other_end() {
+        assert_eq!(with_start(range(0, 10), 20), range(20, 20));
+        assert_eq!(with_end(range(0, 10), -5), range(-5, -5));
+        assert_eq!(with_start(range(0, 10), 5), range(5, 10));
+        assert_eq!(with_end(range(0, 10), 50), range(0, 50));
+    }
+
+    #[test]
+    fn random_updates() {
+        let mut rng = StdRng::seed_from_u64(15);
+        let ends = [i64::MIN, -1 << 40, -1_000_000, -1_000, 0, 1_000, 1_000_000, 1 << 40, i64::MAX];
+        let mut r = range(i64::MIN, i64::MAX);
+        let mut v = uniform(&mut rng, r);
+        for step in 0..200 {
+            let n = ends[rng.gen_range(0..ends.len())].wrapping_add(rng.gen_range(-10..=10));
+            let new = if rng.r#gen() { with_start(r, n) } else { with_end(r, n) };
+            resample(&mut v, r, new, &mut rng, |_| ());
+            r = new;
+            assert!(v.iter().all(|x| r.contains(x)), "step {step}");
+        }
+        // end up in a wide range, so that all the buckets are used
+        let new = range(i64::MIN + 12_345, i64::MAX - 67_890);
+        resample(&mut v, r, new, &mut rng, |_| ());
+        let chi2 = chi_squared(&v, new);
+        assert!(chi2 < CRITICAL, "{r:?} -> {new:?}: chi-squared {chi2}");
+    }
+
+    #[test]
+    fn distributions_stay_in_region() {
+        let mut rng = StdRng::seed_from_u64(16);
+        let one = position(1.0, InputType::F64);
+        let regions = [
+            range(i64::MIN, i64::MAX),
+            range(-one, one),
+            range(0, 0),
+            range(i64::MAX - 1_000, i64::MAX),
+            range(position(3.0, InputType::F64), position(1e300, InputType::F64)),
+        ];
+        for input in [InputType::F32, InputType::F64] {
+            for dist in Distribution::ALL {
+                for r in regions {
+                    for _ in 0..1_000 {
+                        let x = dist.sample(&mut rng, r, input);
+                        assert!(r.contains(&x), "{dist:?}, {input:?}: {x} is outside of {r:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn real_values_are_uniform() {
+        let mut rng = StdRng::seed_from_u64(17);
+        let r = range(position(-3.0, InputType::F64), position(5.0, InputType::F64));
+        let mut counts = [0u64; BUCKETS as usize];
+        for _ in 0..N {
+            let v = value(Distribution::Real.sample(&mut rng, r, InputType::F64), InputType::F64);
+            counts[(((v + 3.0) / 8.0 * BUCKETS as f64) as usize).min(BUCKETS as usize - 1)] += 1;
+        }
+        let e = N as f64 / BUCKETS as f64;
+        let chi2: f64 = counts.iter().map(|&c| (c as f64 - e).powi(2) / e).sum();
+        assert!(chi2 < CRITICAL, "chi-squared {chi2}");
+    }
+
+    #[test]
+    fn edge_cases_are_distinct() {
+        for input in [InputType::F32, InputType::F64] {
+            let v = edge_cases(input);
+            assert!(v.windows(2).all(|w| w[0] < w[1]), "{input:?}: {v:?}");
+            assert!(value(v[0], input).is_nan() && value(v[13], input).is_nan());
+            assert!(v.contains(&position(0.0, input)) && v.contains(&position(-0.0, input)));
+            assert!(v.contains(&position(f64::INFINITY, input)));
+        }
+    }
+}
@@ -0,0 +1,330 @@
+//! Saving and restoring the region, sampling settings, functions and camera
+//!
+//! A session file has one `key = value` setting per line, e.g.
+//! ```text
+//! x = -9223372036854775808 9223372036854775807
+//! input = f32
+//! distribution = log-uniform
+//! seed = 0123456789abcdef
+//! camera.rotation = 0.56 -0.1 -0.15 0.8
+//! function = 3 true 1 0.5 0 1 atan2f/libm ulp
+//! ```
+//! Each `function` line holds the id, visibility, color, z scale and name.
+//! Functions are matched by name, or by id if none has that name.
+
+use std::str::FromStr;
+
+use bevy::prelude::*;
+use bevy_egui::egui::{self, Widget};
+use core::range::RangeInclusive;
+use lib::{Estimator, InputType, Timer, Timing, MAX_REPETITIONS};
+
+use crate::{func_xy::{FuncXY, InputPoints, ParticleMaterial, RegionHistory}, incremental::TimingConfig, orbit_cam::OrbitState, sampling::Distribution, ui::ui_system};
+
+/// Save and load sessions, starting from the file given as the first argument
+pub fn plugin(app: &mut App) {
+    app.init_resource::<Session>()
+        .add_systems(Startup, load_from_args)
+        // the UI resets the visibility of functions when switching to or from 1D
+        .add_systems(Update, (
+            save_load_session.before(ui_system),
+            apply_function_settings.after(ui_system),
+        ));
+}
+
+#[derive(Resource)]
+pub struct Session {
+    path: String,
+    save: bool,
+    load: bool,
+    // the outcome of the last save or load
+    status: String,
+    // loaded settings of functions that have not been spawned yet
+    functions: Vec<FuncSettings>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            path: "cphaze.session".into(),
+            save: false,
+            load: false,
+            status: String::new(),
+            functions: vec![],
+        }
+    }
+}
+
+impl Widget for &mut Session {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        ui.collapsing("Session", |ui| {
+            ui.text_edit_singleline(&mut self.path);
+            ui.horizontal(|ui| {
+                self.save |= ui.button("Save").clicked();
+                self.load |= ui.button("Load").clicked();
+            });
+            if !self.status.is_empty() {
+                ui.label(&self.status);
+            }
+        }).header_response
+    }
+}
+
+#[derive(Clone, Debug)]
+struct FuncSettings {
+    id: u32,
+    name: String,
+    visible: bool,
+    color: [f32; 3],
+    z_scale: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct CameraSettings {
+    target: Vec3,
+    radius: f32,
+    rotation: Quat,
+    auto_rotate_z: bool,
+}
+
+impl From<&OrbitState> for CameraSettings {
+    fn from(c: &OrbitState) -> Self {
+        Self {
+            target: c.target,
+            radius: c.radius,
+            rotation: c.rotation,
+            auto_rotate_z: c.auto_rotate_z,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct SessionFile {
+    x_range: RangeInclusive<i64>,
+    y_range: RangeInclusive<i64>,
+    input: InputType,
+    distribution: Distribution,
+    one_dim: bool,
+    max_len: u32,
+    refresh_random: bool,
+    refresh_rate: u32,
+    exhaustive: bool,
+    edge_cases: bool,
+    timing: Timing,
+    seed: u64,
+    camera: Option<CameraSettings>,
+    functions: Vec<FuncSettings>,
+}
+
+fn parse<T: FromStr>(v: &str) -> Option<T> {
+    v.trim().parse().ok()
+}
+
+fn parse_n<T: FromStr, const N: usize>(v: &str) -> Option<[T; N]> {
+    let v: Vec<T> = v.split_whitespace().map(str::parse).collect::<Result<_, _>>().ok()?;
+    v.try_into().ok()
+}
+
+impl SessionFile {
+    fn to_text(&self) -> String {
+        let mut s = String::from("# cphaze session\n");
+        let mut line = |k: &str, v: String| s.push_str(&format!("{k} = {v}\n"));
+        line("x", format!("{} {}", self.x_range.start, self.x_range.end));
+        line("y", format!("{} {}", self.y_range.start, self.y_range.end));
+        line("input", match self.input {
+            InputType::F32 => "f32",
+            InputType::F64 => "f64",
+        }.into());
+        line("distribution", self.distribution.name().into());
+        line("one_dim", self.one_dim.to_string());
+        line("max_len", self.max_len.to_string());
+        line("refresh_random", self.refresh_random.to_string());
+        line("refresh_rate", self.refresh_rate.to_string());
+        line("exhaustive", self.exhaustive.to_string());
+        line("edge_cases", self.edge_cases.to_string());
+        line("timer", self.timing.timer.name().into());
+        line("repetitions", self.timing.repetitions.to_string());
+        line("estimator", self.timing.estimator.name().into());
+        line("seed", format!("{:016x}", self.seed));
+        if let Some(c) = self.camera {
+            line("camera.target", format!("{} {} {}", c.target.x, c.target.y, c.target.z));
+            line("camera.radius", c.radius.to_string());
+            let [x, y, z, w] = c.rotation.to_array();
+            line("camera.rotation", format!("{x} {y} {z} {w}"));
+            line("camera.auto_rotate_z", c.auto_rotate_z.to_string());
+        }
+        for f in &self.functions {
+            let [r, g, b] = f.color;
+            line("function", format!("{} {} {r} {g} {b} {} {}", f.id, f.visible, f.z_scale, f.name));
+        }
+        s
+    }
+
+    /// Override the settings present in `text`
+    fn parse(&mut self, text: &str) -> Result<(), String> {
+        self.functions.clear();
+        for (n, l) in text.lines().enumerate() {
+            let l = l.trim();
+            if l.is_empty() || l.starts_with('#') { continue }
+            let err = || format!("line {}: invalid setting {l:?}", n + 1);
+            let (k, v) = l.split_once('=').ok_or_else(err)?;
+            let v = v.trim();
+            let camera = self.camera.get_or_insert_with(|| (&OrbitState::default()).into());
+            match k.trim() {
+                "x" => self.x_range = parse_n(v).filter(|[a, b]| a <= b).map(|[a, b]| (a..=b).into()).ok_or_else(err)?,
+                "y" => self.y_range = parse_n(v).filter(|[a, b]| a <= b).map(|[a, b]| (a..=b).into()).ok_or_else(err)?,
+                "input" => self.input = match v {
+                    "f32" => InputType::F32,
+                    "f64" => InputType::F64,
+                    _ => return Err(err()),
+                },
+                "distribution" => self.distribution = Distribution::ALL.into_iter()
+                    .find(|d| d.name() == v)
+                    .ok_or_else(err)?,
+                "one_dim" => self.one_dim = parse(v).ok_or_else(err)?,
+                "max_len" => self.max_len = parse::<u32>(v).ok_or_else(err)?.min(InputPoints::MAX_LEN),
+                "refresh_random" => self.refresh_random = parse(v).ok_or_else(err)?,
+                "refresh_rate" => self.refresh_rate = parse(v).ok_or_else(err)?,
+                "exhaustive" => self.exhaustive = parse(v).ok_or_else(err)?,
+                "edge_cases" => self.edge_cases = parse(v).ok_or_else(err)?,
+                "timer" => self.timing.timer = Timer::ALL.into_iter()
+                    .find(|t| t.name() == v)
+                    .ok_or_else(err)?,
+                "repetitions" => self.timing.repetitions = parse::<u32>(v).ok_or_else(err)?.clamp(1, MAX_REPETITIONS),
+                "estimator" => self.timing.estimator = Estimator::ALL.into_iter()
+                    .find(|e| e.name() == v)
+                    .ok_or_else(err)?,
+                "seed" => self.seed = u64::from_str_radix(v, 16).map_err(|_| err())?,
+                "camera.target" => camera.target = Vec3::from_array(parse_n(v).ok_or_else(err)?),
+                "camera.radius" => camera.radius = parse(v).ok_or_else(err)?,
+                "camera.rotation" => camera.rotation = Quat::from_array(parse_n(v).ok_or_else(err)?).normalize(),
+                "camera.auto_rotate_z" => camera.auto_rotate_z = parse(v).ok_or_else(err)?,
+                "function" => {
+                    let f: Vec<&str> = v.splitn(7, ' ').collect();
+                    let [id, visible, r, g, b, z_scale, name] = f[..] else { return Err(err()) };
+                    self.functions.push(FuncSettings {
+                        id: parse(id).ok_or_else(err)?,
+                        visible: parse(visible).ok_or_else(err)?,
+                        color: [parse(r).ok_or_else(err)?, parse(g).ok_or_else(err)?, parse(b).ok_or_else(err)?],
+                        z_scale: parse(z_scale).ok_or_else(err)?,
+                        name: name.to_owned(),
+                    });
+                }
+                _ => return Err(err()),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn load_from_args(mut session: ResMut<Session>) {
+    if let Some(path) = std::env::args().nth(1) {
+        session.path = path;
+        session.load = true;
+    }
+}
+
+fn save_load_session(
+    mut session: ResMut<Session>,
+    mut points: ResMut<InputPoints>,
+    mut history: ResMut<RegionHistory>,
+    mut timing: ResMut<TimingConfig>,
+    mut cams: Query<&mut OrbitState>,
+    q_func: Query<(&FuncXY, &MeshMaterial3d<ParticleMaterial>, &Visibility)>,
+    materials: Res<Assets<ParticleMaterial>>,
+) {
+    let session = &mut *session;
+    if !session.save && !session.load { return }
+
+    let mut functions: Vec<FuncSettings> = q_func.iter().map(|(f, mat, visibility)| {
+        let mat = materials.get(mat).unwrap();
+        FuncSettings {
+            id: f.id,
+            name: f.desc.name.clone(),
+            visible: *visibility != Visibility::Hidden,
+            color: mat.color.to_f32_array_no_alpha(),
+            z_scale: mat.z_scale,
+        }
+    }).collect();
+    functions.sort_by_key(|f| f.id);
+    let mut file = SessionFile {
+        x_range: points.x_range,
+        y_range: points.y_range,
+        input: points.input,
+        distribution: points.distribution,
+        one_dim: points.one_dim,
+        max_len: points.max_len,
+        refresh_random: points.refresh_random,
+        refresh_rate: points.refresh_rate,
+        exhaustive: points.exhaustive,
+        edge_cases: points.edge_cases,
+        timing: timing.0,
+        seed: points.seed,
+        camera: cams.get_single().ok().map(CameraSettings::from),
+        functions,
+    };
+
+    if std::mem::take(&mut session.save) {
+        session.status = match std::fs::write(&session.path, file.to_text()) {
+            Ok(()) => format!("saved {}", session.path),
+            Err(e) => format!("saving {} failed: {e}", session.path),
+        };
+    }
+    if std::mem::take(&mut session.load) {
+        let loaded = std::fs::read_to_string(&session.path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| file.parse(&text));
+        if let Err(e) = loaded {
+            warn!("Loading session {} failed: {e}", session.path);
+            session.status = format!("loading {} failed: {e}", session.path);
+            return;
+        }
+        history.push(points.region());
+        let points = &mut *points;
+        points.input = file.input;
+        points.distribution = file.distribution;
+        points.one_dim = file.one_dim;
+        points.max_len = file.max_len;
+        points.refresh_random = file.refresh_random;
+        points.refresh_rate = file.refresh_rate;
+        points.exhaustive = file.exhaustive;
+        points.edge_cases = file.edge_cases;
+        timing.0 = file.timing;
+        // the samples are drawn again from the seed, reproducing the saved ones
+        points.reseed(file.seed);
+        points.x_range = file.x_range;
+        points.y_range = file.y_range;
+
+        if let (Some(c), Ok(mut cam)) = (file.camera, cams.get_single_mut()) {
+            cam.target = c.target;
+            cam.radius = c.radius;
+            cam.rotation = c.rotation;
+            cam.auto_rotate_z = c.auto_rotate_z;
+        }
+        session.functions = file.functions;
+        session.status = format!("loaded {}", session.path);
+    }
+}
+
+// Also applies to functions spawned later, e.g. after reloading the lib
+fn apply_function_settings(
+    mut session: ResMut<Session>,
+    mut q_func: Query<(&FuncXY, &MeshMaterial3d<ParticleMaterial>, &mut Visibility)>,
+    mut materials: ResMut<Assets<ParticleMaterial>>,
+) {
+    if session.functions.is_empty() { return }
+    let names: Vec<String> = q_func.iter().map(|(f, ..)| f.desc.name.clone()).collect();
+    for (f, mat, mut visibility) in &mut q_func {
+        // by id only when the saved name is not among the current functions
+        let settings = &session.functions;
+        let Some(i) = settings.iter().position(|s| s.name == f.desc.name)
+            .or_else(|| settings.iter().position(|s| s.id == f.id && !names.contains(&s.name)))
+        else { continue };
+        let s = session.functions.remove(i);
+        let mat = materials.get_mut(mat).unwrap();
+        let [r, g, b] = s.color;
+        mat.color = LinearRgba::new(r, g, b, 0.0);
+        mat.z_scale = s.z_scale;
+        *visibility = if s.visible { Visibility::Inherited } else { Visibility::Hidden };
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::BTreeSet;
+
+use bevy_egui::egui;
+use lib::Total32;
+
+use crate::func_xy::{FuncXY, InputPoints};
+
+// A value with the index of its sample, to tell equal values apart
+type Key = (Total32, u32);
+
+/// Running statistics of a function over the first `len` samples
+///
+/// NaN values and samples not evaluated yet are only counted, the rest are kept ordered in three parts
+/// split at the 50th and 99th percentiles, so that each update is logarithmic.
+pub struct Stats {
+    len: usize,
+    parts: [BTreeSet<Key>; 3],
+    // of the finite values
+    sum: f64,
+    pos_inf: usize,
+    neg_inf: usize,
+}
+
+// The number of values up to the `q` quantile
+fn rank(q: f64, n: usize) -> usize {
+    ((q * n as f64).ceil() as usize).clamp(n.min(1), n)
+}
+
+impl Stats {
+    pub fn new(values: &[f32], len: usize) -> Self {
+        let mut stats = Self {
+            len: 0,
+            parts: Default::default(),
+            sum: 0.0,
+            pos_inf: 0,
+            neg_inf: 0,
+        };
+        stats.resize(values, len);
+        stats
+    }
+
+    /// Follow a change of the number of samples
+    pub fn resize(&mut self, values: &[f32], len: usize) {
+        let value = |i: usize| values.get(i).copied().unwrap_or(f32::NAN);
+        for i in len..self.len {
+            self.remove(value(i), i as u32);
+        }
+        for i in self.len..len {
+            self.insert(value(i), i as u32);
+        }
+        self.len = len;
+    }
+
+    /// Replace the value of sample `i`
+    pub fn replace(&mut self, old: f32, new: f32, i: u32) {
+        if (i as usize) < self.len {
+            self.remove(old, i);
+            self.insert(new, i);
+        }
+    }
+
+    fn insert(&mut self, v: f32, i: u32) {
+        if v.is_nan() { return }
+        self.add(v, 1);
+        let k = (Total32::from(v), i);
+        let [p0, p1, p2] = &mut self.parts;
+        if p0.last().is_some_and(|&m| k < m) {
+            p0.insert(k);
+        } else if p1.last().is_some_and(|&m| k < m) {
+            p1.insert(k);
+        } else {
+            p2.insert(k);
+        }
+        self.rebalance();
+    }
+
+    fn remove(&mut self, v: f32, i: u32) {
+        if v.is_nan() { return }
+        let k = (Total32::from(v), i);
+        if self.parts.iter_mut().any(|p| p.remove(&k)) {
+            self.add(v, -1);
+            self.rebalance();
+        }
+    }
+
+    // `sign` is 1 when inserting and -1 when removing
+    fn add(&mut self, v: f32, sign: isize) {
+        if v == f32::INFINITY {
+            self.pos_inf = self.pos_inf.wrapping_add_signed(sign);
+        } else if v == f32::NEG_INFINITY {
+            self.neg_inf = self.neg_inf.wrapping_add_signed(sign);
+        } else {
+            self.sum += (sign as f64) * v as f64;
+        }
+    }
+
+    fn rebalance(&mut self) {
+        let n = self.count();
+        let (a, b) = (rank(0.5, n), rank(0.99, n));
+        let [p0, p1, p2] = &mut self.parts;
+        while p0.len() > a {
+            p1.insert(p0.pop_last().unwrap());
+        }
+        while p0.len() < a {
+            p0.insert(p1.pop_first().or_else(|| p2.pop_first()).unwrap());
+        }
+        while p0.len() + p1.len() > b {
+            p2.insert(p1.pop_last().unwrap());
+        }
+        while p0.len() + p1.len() < b {
+            p1.insert(p2.pop_first().unwrap());
+        }
+    }
+
+    /// The number of values that are not NaN
+    pub fn count(&self) -> usize {
+        self.parts.iter().map(BTreeSet::len).sum()
+    }
+
+    pub fn nan(&self) -> usize {
+        self.len - self.count()
+    }
+
+    pub fn min(&self) -> Option<f32> {
+        self.parts.iter().find_map(|p| p.first()).map(|k| k.0.into())
+    }
+
+    /// The largest value, with the index of its sample
+    pub fn max(&self) -> Option<(f32, u32)> {
+        self.parts.iter().rev().find_map(|p| p.last()).map(|k| (k.0.into(), k.1))
+    }
+
+    pub fn p50(&self) -> Option<f32> {
+        self.parts[0].last().map(|k| k.0.into())
+    }
+
+    pub fn p99(&self) -> Option<f32> {
+        self.parts[1].last().or(self.parts[0].last()).map(|k| k.0.into())
+    }
+
+    pub fn mean(&self) -> Option<f32> {
+        let n = self.count();
+        if n == 0 { return None }
+        Some(match (self.pos_inf, self.neg_inf) {
+            (0, 0) => (self.sum / n as f64) as f32,
+            (_, 0) => f32::INFINITY,
+            (0, _) => f32::NEG_INFINITY,
+            _ => f32::NAN,
+        })
+    }
+}
+
+/// The statistics of `func`, tracked while enabled
+pub fn stats_ui(ui: &mut egui::Ui, func: &mut FuncXY, points: &InputPoints) {
+    let mut track = func.stats.is_some();
+    ui.checkbox(&mut track, &func.desc.name);
+    if track != func.stats.is_some() {
+        func.stats = track.then(|| Stats::new(&func.values, points.x_vec.len()));
+    }
+    let Some(stats) = &func.stats else { return };
+
+    let fmt = |v: Option<f32>| v.map_or("-".to_owned(), |v| format!("{v:.6e}"));
+    egui::Grid::new(("stats", func.id)).show(ui, |ui| {
+        for (label, v) in [
+            ("min", stats.min()),
+            ("p50", stats.p50()),
+            ("p99", stats.p99()),
+            ("max", stats.max().map(|m| m.0)),
+            ("mean", stats.mean()),
+        ] {
+            ui.label(label);
+            ui.monospace(fmt(v));
+            ui.end_row();
+        }
+        ui.label("NaN");
+        ui.monospace(stats.nan().to_string());
+        ui.end_row();
+        // unless the worst sample has been moved since
+        if let Some((_, i)) = stats.max() && points.evaluated(i) {
+            let i = i as usize;
+            if let (Some(&x), Some(&y)) = (points.x_vec.get(i), points.y_vec.get(i)) {
+                ui.label("worst");
+                ui.monospace(format!("x = {}", points.fmt(x)));
+                ui.end_row();
+                if !points.one_dim {
+                    ui.label("");
+                    ui.monospace(format!("y = {}", points.fmt(y)));
+                    ui.end_row();
+                }
+            }
+        }
+    });
+}
@@ -4,25 +4,57 @@ use std::panic::BacktraceStyle;
 
 use bevy::prelude::*;
 use bevy_egui::{egui::{self, Ui},EguiContexts};
+use lib::{InputType, Kind, NanMode, TimingMode};
 
-use crate::{func_xy::{FuncXY, InputPoints, ParticleMaterial, RegionUpdate}, orbit_cam::OrbitState};
+use core::range::RangeInclusive;
 
+use crate::{adaptive::Adaptive, export::Export, incremental::TimingConfig, histogram::HistogramView, session::Session, stats::stats_ui, func_xy::{set_region, FuncXY, InputPoints, ParticleMaterial, Region, RegionHistory, RegionUpdate}, orbit_cam::OrbitState};
 
+// The longest prefix of at most `width` bits shared by all of `r`, as (unset, set) bits
+fn prefix_bits(r: RangeInclusive<i64>, width: u32) -> (i64, i64) {
+    let a = r.start ^ i64::MIN;
+    let b = r.end ^ i64::MIN;
+    let len = (a ^ b).leading_zeros().min(width);
+    let mask = if len == 0 { 0 } else { i64::MIN >> (len - 1) };
+    (!a & mask, a & mask)
+}
+
+fn bits_range((unset, set): (i64, i64)) -> RangeInclusive<i64> {
+    (set ^ i64::MIN..=!unset ^ i64::MIN).into()
+}
+
+// The text of the bound fields: x0, x1, y0, y1
+#[derive(Default)]
+pub struct BoundsEdit {
+    texts: [String; 4],
+    invalid: bool,
+}
+
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn ui_system(
     mut contexts: EguiContexts,
     mut commands: Commands,
     mut points: ResMut<InputPoints>,
-    mut x_bits: Local<(i32, i32)>,
-    mut y_bits: Local<(i32, i32)>,
-    mut q_func: Query<(&FuncXY,&MeshMaterial3d<ParticleMaterial>, &mut Visibility)>,
+    mut x_bits: Local<(i64, i64)>,
+    mut y_bits: Local<(i64, i64)>,
+    mut arity: Local<u32>,
+    mut last_region: Local<Option<(Region, InputType)>>,
+    mut bounds_edit: Local<BoundsEdit>,
+    mut history: ResMut<RegionHistory>,
+    mut q_func: Query<(Entity, &mut FuncXY, &MeshMaterial3d<ParticleMaterial>, &mut Visibility, Has<HistogramView>)>,
     mut materials: ResMut<Assets<ParticleMaterial>>,
     mut cams: Query<&mut OrbitState>,
+    mut export: ResMut<Export>,
+    mut session: ResMut<Session>,
+    mut adaptive: ResMut<Adaptive>,
+    mut timing: ResMut<TimingConfig>,
 ) {
     let points = &mut *points;
     egui::Window::new("Controls").show(contexts.ctx_mut(), |ui| {
 
         let button = |ui: &mut Ui, set, unset, chars: &str| {
-            let mut state = (set as i32) - (unset as i32);
+            let mut state = (set as i64) - (unset as i64);
             let i = (1 + state) as usize;
             let r = ui.small_button(&chars[i..i+1]);
             if r.clicked_by(egui::PointerButton::Primary) {
@@ -38,9 +70,46 @@ pub fn ui_system(
                     state = 0;
                 }
             }
-            ((state > 0) as i32, (state < 0) as i32)
+            ((state > 0) as i64, (state < 0) as i64)
         };
 
+        // bits past the selected precision are left unconstrained
+        let bits = points.input.bits();
+
+        let mode_changed = *arity != points.arity();
+        *arity = points.arity();
+
+        // follow changes of the region made elsewhere, e.g. by zooming
+        let region = points.region();
+        if *last_region != Some((region, points.input)) {
+            *x_bits = prefix_bits(points.x_range, bits);
+            if !points.one_dim {
+                *y_bits = prefix_bits(points.y_range, bits);
+            }
+            let (x, y) = region;
+            bounds_edit.texts = [x.start, x.end, y.start, y.end].map(|b| points.bound_text(b));
+            bounds_edit.invalid = false;
+            *last_region = Some((region, points.input));
+        }
+        let old_bits = (*x_bits, *y_bits);
+
+        // in 1D mode, y stays pinned to 0
+        let one_dim = points.one_dim;
+        let pin_y = |(x, y): Region| (x, if one_dim { (0..=0).into() } else { y });
+
+        ui.horizontal(|ui| {
+            if ui.add_enabled(history.can_back(), egui::Button::new("back")).clicked()
+                && let Some(r) = history.back(region)
+            {
+                set_region(&mut commands, region, pin_y(r));
+            }
+            if ui.add_enabled(history.can_forward(), egui::Button::new("forward")).clicked()
+                && let Some(r) = history.forward(region)
+            {
+                set_region(&mut commands, region, pin_y(r));
+            }
+        });
+
         ui.horizontal(|ui| {
             ui.label("x: ");
 
@@ -53,7 +122,7 @@ pub fn ui_system(
             ns += s;  nu += u; 
             os <<= 1; ou <<= 1;
 
-            for _ in 1..32 {
+            for _ in 1..bits {
                 ns <<= 1; nu <<= 1;
                 if s | u != 0 {
                     (s,u) = button(ui, os < 0, ou < 0, "0 1");
@@ -61,62 +130,101 @@ pub fn ui_system(
                 }
                 os <<= 1; ou <<= 1;
             }
+            ns <<= 64 - bits; nu <<= 64 - bits;
             *x_bits = (nu,ns);
         });
-        ui.horizontal(|ui| {
-            ui.label("y: ");
+        if !points.one_dim {
+            ui.horizontal(|ui| {
+                ui.label("y: ");
 
-            let mut os = y_bits.1;
-            let mut ou = y_bits.0;
-            let mut ns = 0;
-            let mut nu = 0;
-            ns <<= 1; nu <<= 1;
-            let (mut s, mut u) = button(ui, os < 0, ou < 0, "- +");
-            ns += s;  nu += u; 
-            os <<= 1; ou <<= 1;
-
-            for _ in 1..32 {
+                let mut os = y_bits.1;
+                let mut ou = y_bits.0;
+                let mut ns = 0;
+                let mut nu = 0;
                 ns <<= 1; nu <<= 1;
-                if s | u != 0 {
-                    (s,u) = button(ui, os < 0, ou < 0, "0 1");
-                    ns += s; nu += u;
-                }
+                let (mut s, mut u) = button(ui, os < 0, ou < 0, "- +");
+                ns += s;  nu += u; 
                 os <<= 1; ou <<= 1;
-            }
-            *y_bits = (nu,ns);
-        });
-
-        let min = x_bits.1 ^ i32::MIN;
-        let max = !x_bits.0 ^ i32::MIN;
-
-        commands.trigger(RegionUpdate::StartX(min));
-        commands.trigger(RegionUpdate::EndX(max));
 
-        let min = y_bits.1 ^ i32::MIN;
-        let max = !y_bits.0 ^ i32::MIN;
+                for _ in 1..bits {
+                    ns <<= 1; nu <<= 1;
+                    if s | u != 0 {
+                        (s,u) = button(ui, os < 0, ou < 0, "0 1");
+                        ns += s; nu += u;
+                    }
+                    os <<= 1; ou <<= 1;
+                }
+                ns <<= 64 - bits; nu <<= 64 - bits;
+                *y_bits = (nu,ns);
+            });
+        }
 
-        commands.trigger(RegionUpdate::StartY(min));
-        commands.trigger(RegionUpdate::EndY(max));
+        let bits_changed = (*x_bits, *y_bits) != old_bits;
+        if bits_changed {
+            history.push(region);
+        }
+        if bits_changed || mode_changed {
+            set_region(&mut commands, region, pin_y((bits_range(*x_bits), bits_range(*y_bits))));
+        }
 
-        for (s, x) in [
-            ("x0: ", points.x_range.start),
-            ("x1: ", points.x_range.end),
-            ("y0: ", points.y_range.start),
-            ("y1: ", points.y_range.end),
-        ] {
-            ui.monospace(format!("{s}{}", crate::hot::int_fmt(x)));
+        // arbitrary bounds, applied with enter
+        let shown = if points.one_dim { 1 } else { 2 };
+        let mut apply = false;
+        for (axis, texts) in ["x", "y"].into_iter().zip(bounds_edit.texts.chunks_mut(2)).take(shown) {
+            ui.horizontal(|ui| {
+                ui.label(format!("{axis} from"));
+                let r0 = ui.add(egui::TextEdit::singleline(&mut texts[0]).font(egui::TextStyle::Monospace).desired_width(180.0));
+                ui.label("to");
+                let r1 = ui.add(egui::TextEdit::singleline(&mut texts[1]).font(egui::TextStyle::Monospace).desired_width(180.0));
+                let enter = ui.input(|i| i.key_pressed(egui::Key::Enter));
+                apply |= (r0.lost_focus() || r1.lost_focus()) && enter;
+            });
+        }
+        if apply {
+            let [x0, x1, y0, y1] = [(0, false), (1, true), (2, false), (3, true)]
+                .map(|(i, end)| points.parse_bound(&bounds_edit.texts[i], end));
+            match (x0, x1, y0, y1) {
+                (Some(x0), Some(x1), Some(y0), Some(y1)) if x0 <= x1 && y0 <= y1 => {
+                    let new = pin_y(((x0..=x1).into(), (y0..=y1).into()));
+                    if new != region {
+                        history.push(region);
+                        set_region(&mut commands, region, new);
+                    }
+                    bounds_edit.invalid = false;
+                }
+                _ => bounds_edit.invalid = true,
+            }
+        }
+        if bounds_edit.invalid {
+            ui.colored_label(egui::Color32::RED, "invalid bounds");
         }
 
         ui.group(|ui| {
             ui.add(&mut *points);
         });
 
-        if points.max_len != points.x_vec.len() as u32 {
+        ui.collapsing("Adaptive sampling", |ui| {
+            let arity = points.arity();
+            let names = q_func.iter().filter(|f| f.1.desc.arity == arity).map(|f| f.1.desc.name.as_str());
+            adaptive.ui(ui, names);
+        });
+
+        if points.enumerated.is_none() && points.max_len != points.x_vec.len() as u32 {
             commands.trigger(RegionUpdate::SetLen(points.max_len));
         }
 
+        // functions of the other arity are hidden, and shown again when switching back
+        for (_, f, _, mut visibility, _) in q_func.iter_mut() {
+            if f.desc.arity != points.arity() {
+                *visibility = Visibility::Hidden;
+            } else if mode_changed {
+                *visibility = Visibility::Inherited;
+            }
+        }
+
         ui.collapsing("Functions", |ui| {
-            for (_f, mat, mut visibility) in q_func.iter_mut() {
+            for (entity, mut f, mat, mut visibility, hist) in q_func.iter_mut() {
+                if f.desc.arity != points.arity() { continue }
                 ui.horizontal(|ui| {
 
                     let mut show = *visibility != Visibility::Hidden;
@@ -128,15 +236,70 @@ pub fn ui_system(
                     }
 
                     ui.add(materials.get_mut(mat).unwrap());
+                    if ui.selectable_label(hist, "hist").clicked() {
+                        if hist {
+                            commands.entity(entity).remove::<HistogramView>();
+                            materials.get_mut(mat).unwrap().highlight = ParticleMaterial::NO_HIGHLIGHT;
+                        } else {
+                            commands.entity(entity).insert(HistogramView::default());
+                        }
+                    }
+                    ui.label(&f.desc.name);
+                    if f.desc.kind == Kind::Comparison {
+                        let mat = materials.get_mut(mat).unwrap();
+                        let mut mismatches = mat.mismatches != 0;
+                        ui.checkbox(&mut mismatches, "mismatches");
+                        mat.mismatches = mismatches as u32;
+                        let modes = [(NanMode::Any, "any NaN"), (NanMode::Sign, "NaN sign"), (NanMode::Payload, "NaN bits")];
+                        let nan = f.nan;
+                        egui::ComboBox::from_id_salt(("nan", f.id))
+                            .selected_text(modes.iter().find(|m| m.0 == nan).map_or("", |m| m.1))
+                            .show_ui(ui, |ui| {
+                                for (mode, name) in modes {
+                                    ui.selectable_value(&mut f.nan, mode, name);
+                                }
+                            });
+                    }
+                    if f.desc.kind == Kind::Timing {
+                        let mode = f.timing_mode;
+                        egui::ComboBox::from_id_salt(("timing mode", f.id))
+                            .selected_text(mode.name())
+                            .show_ui(ui, |ui| {
+                                for m in TimingMode::ALL {
+                                    ui.selectable_value(&mut f.timing_mode, m, m.name());
+                                }
+                            });
+                        if f.timing_mode != mode {
+                            // measured again in the new mode
+                            points.modified.extend(0..points.x_vec.len() as u32);
+                        }
+                    }
                 });
             }
 
+            ui.collapsing("Statistics", |ui| {
+                for (_, mut f, ..) in q_func.iter_mut() {
+                    if f.desc.arity != points.arity() { continue }
+                    stats_ui(ui, &mut f, points);
+                }
+            });
+
         });
         
         for mut cam in cams.iter_mut() {
             ui.add(&mut *cam);
         }
 
+        let old_timing = timing.0;
+        ui.add(&mut *timing);
+        if timing.0 != old_timing {
+            // measured again with the new settings
+            points.modified.extend(0..points.x_vec.len() as u32);
+        }
+
+        ui.add(&mut *export);
+        ui.add(&mut *session);
+
         if let Some(mut bt) = std::panic::get_backtrace_style() {
             ui.collapsing("Backtraces", |ui| {
                 let old = bt;
@@ -0,0 +1,203 @@
+//! The thread evaluating the test functions, off the frame
+//!
+//! `incremental` sends it a batch of points with the functions to evaluate,
+//! and uploads the results when they come back, so that slow functions do not
+//! stall rendering and timings are not disturbed by the task pool. With
+//! `CPHAZE_CPU` set to the index of a CPU, the thread is pinned to it; for quiet
+//! timings that CPU is best isolated from the scheduler, e.g. with `isolcpus`.
+//!
+//! The functions that are not timed are split into chunks evaluated in parallel
+//! by a pool with a thread per CPU, after which the timing functions run alone on
+//! the worker thread. Without timing functions, batches are as many times larger.
+//! The pool keeps off the CPU given by `CPHAZE_CPU`, when there are others.
+
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+
+use bevy::prelude::*;
+use bevy::tasks::{TaskPool, TaskPoolBuilder};
+use lib::{Kind, Timing};
+
+use crate::incremental::{evaluate, Call};
+
+/// Points to evaluate the functions at
+pub struct Batch {
+    /// The indices of the points in `InputPoints`
+    pub idxs: Vec<u32>,
+    pub xs: Vec<i64>,
+    pub ys: Vec<i64>,
+    pub timing: Timing,
+    /// The functions to evaluate, with the function receiving the noise of a timing
+    pub calls: Vec<(Entity, Call, Option<Entity>)>,
+}
+
+/// A batch with the results of each of its calls, in order, and the noise of
+/// the timings sent with a function to receive it
+pub struct Done {
+    pub batch: Batch,
+    pub results: Vec<(Vec<f32>, Vec<f32>)>,
+}
+
+/// The channels to the worker thread, which exits when they are dropped
+pub struct Worker {
+    jobs: Sender<Batch>,
+    done: Receiver<Done>,
+    busy: bool,
+    threads: usize,
+}
+
+impl Default for Worker {
+    fn default() -> Self {
+        let (jobs, rx) = channel::<Batch>();
+        let (tx, done) = channel();
+        let cpu = std::env::var("CPHAZE_CPU").ok().map(|v| (v.parse::<usize>(), v));
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        std::thread::Builder::new()
+            .name("evaluation".into())
+            .spawn(move || {
+                // spawned before pinning, and kept off the pinned CPU if there are others;
+                // in any case the timings run once the pool is done
+                let others = match cpu {
+                    Some((Ok(cpu), _)) => Cpus::without(cpu),
+                    _ => None,
+                };
+                let pool = TaskPoolBuilder::new()
+                    .num_threads(threads)
+                    .thread_name("evaluation pool".into())
+                    .on_thread_spawn(move || {
+                        if let Some(others) = others {
+                            others.apply();
+                        }
+                    })
+                    .build();
+                match cpu {
+                    Some((Ok(cpu), _)) if Cpus::only(cpu).is_some_and(Cpus::apply) => info!("Evaluating on CPU {cpu}"),
+                    Some((_, v)) => warn!("Could not pin the evaluation to CPU {v:?}"),
+                    None => {}
+                }
+                for batch in rx {
+                    let results = evaluate_batch(&batch, &pool);
+                    if tx.send(Done { batch, results }).is_err() { break }
+                }
+            })
+            .expect("failed to spawn the evaluation thread");
+        Self { jobs, done, busy: false, threads }
+    }
+}
+
+impl Worker {
+    /// Whether a batch is being evaluated
+    pub fn busy(&self) -> bool {
+        self.busy
+    }
+
+    /// The threads evaluating the functions that are not timed
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    pub fn send(&mut self, batch: Batch) {
+        self.busy = self.jobs.send(batch).is_ok();
+    }
+
+    /// The evaluated batch, once it is done
+    pub fn try_recv(&mut self) -> Option<Done> {
+        match self.done.try_recv() {
+            Ok(done) => {
+                self.busy = false;
+                Some(done)
+            }
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                self.busy = false;
+                None
+            }
+        }
+    }
+}
+
+// the fewest points evaluated by each task
+const MIN_CHUNK: usize = 1 << 6;
+
+// The results of each call of `batch`, evaluating the calls that are not timed on `pool`
+fn evaluate_batch(batch: &Batch, pool: &TaskPool) -> Vec<(Vec<f32>, Vec<f32>)> {
+    let n = batch.xs.len();
+    let mut results: Vec<_> = batch.calls.iter()
+        .map(|(_, _, noise)| (vec![0.0; n], if noise.is_some() { vec![0.0; n] } else { vec![] }))
+        .collect();
+    let chunk = n.div_ceil(pool.thread_num()).max(MIN_CHUNK);
+    let mut timed = vec![];
+    // the pinned worker thread does not take part
+    pool.scope_with_executor(false, None, |s| {
+        for ((_, call, _), (r, spread)) in batch.calls.iter().zip(&mut results) {
+            if call.kind == Kind::Timing {
+                timed.push((call, r, spread));
+                continue;
+            }
+            for ((xs, ys), r) in batch.xs.chunks(chunk).zip(batch.ys.chunks(chunk)).zip(r.chunks_mut(chunk)) {
+                s.spawn(async move {
+                    evaluate(call, batch.timing, xs, ys, r, &mut []);
+                });
+            }
+        }
+    });
+    // the timings run alone, once the others are done
+    for (call, r, spread) in timed {
+        evaluate(call, batch.timing, &batch.xs, &batch.ys, r, spread);
+    }
+    results
+}
+
+// The CPUs a thread may run on
+#[cfg(target_os = "linux")]
+#[derive(Clone, Copy)]
+struct Cpus(libc::cpu_set_t);
+
+#[cfg(target_os = "linux")]
+impl Cpus {
+    /// The CPUs the calling thread may run on, except `cpu`
+    fn without(cpu: usize) -> Option<Self> {
+        if cpu >= libc::CPU_SETSIZE as usize { return None }
+        // SAFETY: the set is a plain bit mask, and 0 is the calling thread
+        unsafe {
+            let mut set: libc::cpu_set_t = std::mem::zeroed();
+            if libc::sched_getaffinity(0, size_of::<libc::cpu_set_t>(), &mut set) != 0 { return None }
+            libc::CPU_CLR(cpu, &mut set);
+            Some(Self(set))
+        }
+    }
+
+    fn only(cpu: usize) -> Option<Self> {
+        if cpu >= libc::CPU_SETSIZE as usize { return None }
+        // SAFETY: the set is a plain bit mask
+        unsafe {
+            let mut set: libc::cpu_set_t = std::mem::zeroed();
+            libc::CPU_SET(cpu, &mut set);
+            Some(Self(set))
+        }
+    }
+
+    /// Restrict the calling thread to these CPUs
+    fn apply(self) -> bool {
+        // SAFETY: 0 is the calling thread
+        unsafe { libc::sched_setaffinity(0, size_of::<libc::cpu_set_t>(), &self.0) == 0 }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+#[derive(Clone, Copy)]
+struct Cpus;
+
+#[cfg(not(target_os = "linux"))]
+impl Cpus {
+    fn without(_cpu: usize) -> Option<Self> {
+        None
+    }
+
+    fn only(_cpu: usize) -> Option<Self> {
+        None
+    }
+
+    fn apply(self) -> bool {
+        false
+    }
+}
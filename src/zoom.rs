@@ -0,0 +1,58 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{func_xy::{f2i, set_region, InputPoints, RegionHistory}, pick::plane_point};
+
+/// Shift-drag on the plane to zoom into the selected rectangle
+pub fn plugin(app: &mut App) {
+    app.add_systems(Update, zoom_to_selection);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn zoom_to_selection(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    kbd: Res<ButtonInput<KeyCode>>,
+    click: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    cams: Query<(&Camera, &GlobalTransform)>,
+    points: Res<InputPoints>,
+    mut history: ResMut<RegionHistory>,
+    mut start: Local<Option<Vec2>>,
+) {
+    let shift = kbd.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if click.just_pressed(MouseButton::Left) && shift && !contexts.ctx_mut().wants_pointer_input() {
+        *start = plane_point(&windows, &cams);
+    }
+    let Some(a) = *start else { return };
+    let Some(b) = plane_point(&windows, &cams) else { return };
+    // the plane spans [-1, 1] in both axes
+    let lo = a.min(b).clamp(Vec2::NEG_ONE, Vec2::ONE);
+    let hi = a.max(b).clamp(Vec2::NEG_ONE, Vec2::ONE);
+
+    if !click.pressed(MouseButton::Left) {
+        *start = None;
+        if hi.x <= lo.x || (!points.one_dim && hi.y <= lo.y) { return }
+        let x = points.snap(f2i(lo.x, points.x_range), f2i(hi.x, points.x_range));
+        let y = if points.one_dim {
+            points.y_range
+        } else {
+            points.snap(f2i(lo.y, points.y_range), f2i(hi.y, points.y_range))
+        };
+        history.push(points.region());
+        set_region(&mut commands, points.region(), (x, y));
+        return;
+    }
+
+    let Ok((camera, transform)) = cams.get_single() else { return };
+    let corners: Option<Vec<egui::Pos2>> = [lo, Vec2::new(hi.x, lo.y), hi, Vec2::new(lo.x, hi.y)]
+        .into_iter()
+        .map(|p| {
+            let v = camera.world_to_viewport(transform, p.extend(0.0)).ok()?;
+            Some(egui::pos2(v.x, v.y))
+        })
+        .collect();
+    let Some(corners) = corners else { return };
+    let painter = contexts.ctx_mut().layer_painter(egui::LayerId::background());
+    painter.add(egui::Shape::closed_line(corners, egui::Stroke::new(1.0_f32, egui::Color32::WHITE)));
+}